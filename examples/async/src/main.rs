@@ -18,24 +18,11 @@ use rusqlite::params;
 use rusqlite_migration::{Migrations, M};
 use tokio_rusqlite_new::Connection;
 
-/// The general idea with this example is to use [`Connection::call`][call] and
-/// [`Connection::call_unwrap`][call_unwrap] to run the migration in a sync context.
-///
-/// [call]: https://docs.rs/tokio-rusqlite/0.6.0/tokio_rusqlite/struct.Connection.html#method.call
-/// [call_unwrap]: https://docs.rs/tokio-rusqlite/0.6.0/tokio_rusqlite/struct.Connection.html#method.call_unwrap
-
-// Test that migrations are working
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    // Validating that migrations are correctly defined. It is enough to test in the sync context,
-    // because under the hood, tokio_rusqlite executes the migrations in a sync context anyway.
-    #[test]
-    fn migrations_test() {
-        assert!(MIGRATIONS.validate().is_ok());
-    }
-}
+// The general idea with this example is to use [`Connection::call`][call] and
+// [`Connection::call_unwrap`][call_unwrap] to run the migration in a sync context.
+//
+// [call]: https://docs.rs/tokio-rusqlite/0.6.0/tokio_rusqlite/struct.Connection.html#method.call
+// [call_unwrap]: https://docs.rs/tokio-rusqlite/0.6.0/tokio_rusqlite/struct.Connection.html#method.call_unwrap
 
 // Define migrations. These are applied atomically.
 const MIGRATION_ARRAY: &[M] = &[
@@ -131,3 +118,16 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+// Test that migrations are working
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Validating that migrations are correctly defined. It is enough to test in the sync context,
+    // because under the hood, tokio_rusqlite executes the migrations in a sync context anyway.
+    #[test]
+    fn migrations_test() {
+        assert!(MIGRATIONS.validate().is_ok());
+    }
+}
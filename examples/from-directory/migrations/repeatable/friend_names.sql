@@ -0,0 +1,2 @@
+DROP VIEW IF EXISTS friend_names;
+CREATE VIEW friend_names AS SELECT name FROM friend;
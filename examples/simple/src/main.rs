@@ -17,17 +17,6 @@ use anyhow::Result;
 use rusqlite::{params, Connection};
 use rusqlite_migration::{Migrations, M};
 
-// Test that migrations are working
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn migrations_test() {
-        assert!(MIGRATIONS.validate().is_ok());
-    }
-}
-
 // Define migrations. These are applied atomically.
 const MIGRATION_ARRAY: &[M] = &[
     M::up(include_str!("../../friend_car.sql")),
@@ -84,3 +73,14 @@ pub fn main() {
     conn.execute("INSERT INTO animal (name) VALUES (?1)", params!["cat"])
         .unwrap_err();
 }
+
+// Test that migrations are working
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_test() {
+        assert!(MIGRATIONS.validate().is_ok());
+    }
+}
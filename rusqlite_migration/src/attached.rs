@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migrate attached databases
+
+use log::{debug, info, trace, warn};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::{
+    sql::quote_identifier, user_version_in, Error, MigrationDefinitionError, Migrations, Result,
+};
+
+impl Migrations<'_> {
+    /// Migrate the database attached as `schema` to the connection, instead of the `main` one.
+    /// Its version is read and written with `PRAGMA <schema>.user_version` and foreign keys are
+    /// checked in that schema only.
+    ///
+    /// The SQL of the migrations is run as is: statements creating objects must name the
+    /// schema, as in `CREATE TABLE cache.items (…)`. Statements in a transaction can’t attach
+    /// or detach databases, so `schema` must be attached before migrating. See
+    /// [`Migrations::to_latest_all`] to migrate several schemas in a single transaction.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute("ATTACH DATABASE ':memory:' AS cache", []).unwrap();
+    ///
+    /// let migrations = Migrations::new(vec![M::up("CREATE TABLE cache.items (key TEXT);")])
+    ///     .schema("cache");
+    /// migrations.to_latest(&mut conn).unwrap();
+    ///
+    /// let version: i64 = conn.query_row("PRAGMA cache.user_version", [], |r| r.get(0)).unwrap();
+    /// assert_eq!(version, 1);
+    /// ```
+    #[must_use]
+    pub fn schema(mut self, schema: impl Into<String>) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    /// Migrate several sets of migrations to their latest version, in a single transaction.
+    /// Typically, each set targets a different schema, see [`Migrations::schema`]. Either all
+    /// the sets are migrated, or none is.
+    ///
+    /// Backups are not made, even when configured with `Migrations::backup`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute("ATTACH DATABASE ':memory:' AS cache", []).unwrap();
+    ///
+    /// let main = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    /// let cache = Migrations::new(vec![M::up("CREATE TABLE cache.items (key TEXT);")])
+    ///     .schema("cache");
+    /// Migrations::to_latest_all(&mut conn, &[&main, &cache]).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of the sets, see [`Migrations::to_latest`]. The transaction is
+    /// then rolled back, for all the sets.
+    pub fn to_latest_all(conn: &mut Connection, sets: &[&Migrations<'_>]) -> Result<()> {
+        trace!("start migration transaction");
+        let tx = conn.transaction()?;
+        for &set in sets {
+            let linear;
+            let set = if set.is_graph() {
+                linear = set.linearized(&tx)?;
+                &linear
+            } else {
+                set
+            };
+            if set.ms.is_empty() {
+                warn!("no migration defined");
+                return Err(Error::MigrationDefinition(
+                    MigrationDefinitionError::NoMigrationsDefined,
+                ));
+            }
+            let current_version = user_version_in(&tx, set.schema.as_deref())?;
+            let target_version = set.ms.len();
+            set.check_downgrade(current_version, target_version)?;
+            set.check_modules(&tx, current_version)?;
+            if current_version > target_version {
+                if !set.store_down {
+                    return Err(Error::MigrationDefinition(
+                        MigrationDefinitionError::DatabaseTooFarAhead,
+                    ));
+                }
+                set.check_down_defined(current_version, target_version)?;
+                set.migrate_down(&tx, current_version, target_version)?;
+            } else if current_version < target_version {
+                set.migrate_up(&tx, current_version, target_version)?;
+            } else {
+                debug!(
+                    "no migration to run in {}, already up to date",
+                    set.schema_name()
+                );
+            }
+            set.run_repeatable(&tx)?;
+        }
+        tx.commit()?;
+        trace!("committed migration transaction");
+        info!("Databases migrated");
+        Ok(())
+    }
+
+    /// Name of the schema migrated
+    pub(crate) fn schema_name(&self) -> &str {
+        self.schema.as_deref().unwrap_or("main")
+    }
+
+    /// Name of a table of this library, in the schema migrated
+    pub(crate) fn qualified(&self, table: &str) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{table}", quote_identifier(schema)),
+            None => String::from(table),
+        }
+    }
+
+    /// Open a temporary in-memory database, with another one attached under the name of the schema
+    /// migrated, if any
+    pub(crate) fn open_scratch(&self) -> Result<Connection> {
+        let conn = Connection::open_in_memory()?;
+        if let Some(schema) = &self.schema {
+            let attach = format!("ATTACH DATABASE ':memory:' AS {}", quote_identifier(schema));
+            conn.execute_batch(&attach)
+                .map_err(|e| Error::with_sql(e, &attach))?;
+        }
+        Ok(conn)
+    }
+
+    /// Whether a table of this library exists in the schema migrated
+    pub(crate) fn table_exists(&self, conn: &Connection, table: &str) -> Result<bool> {
+        let exists = format!(
+            "SELECT 1 FROM {} WHERE type = 'table' AND name = '{table}'",
+            self.qualified("sqlite_master")
+        );
+        Ok(conn
+            .query_row(&exists, [], |_| Ok(()))
+            .optional()
+            .map_err(|e| Error::with_sql(e, &exists))?
+            .is_some())
+    }
+}
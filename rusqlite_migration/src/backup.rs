@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Back up the database before migrating it
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, error, info, warn};
+use rusqlite::{backup::Progress, Connection};
+
+use crate::{Error, Result};
+
+/// Backups taken before running migrations, see [`crate::Migrations::backup`].
+///
+/// Transactions roll back the changes made to the database when a migration fails, but some
+/// statements are not transactional and hooks may have other side effects. With a backup, the
+/// database can be restored to its exact state before the migrations.
+///
+/// Backups are made with the [online backup API][backup] of SQLite, to files named
+/// `{prefix}-{timestamp}-v{version}.sqlite3` in a given directory, where `version` is the
+/// version of the database before the migrations.
+///
+/// [backup]: https://www.sqlite.org/backup.html
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[must_use]
+pub struct Backup {
+    dir: PathBuf,
+    prefix: String,
+    keep_last: Option<usize>,
+    restore: bool,
+}
+
+impl Backup {
+    /// Write backups to the directory `dir`, which must exist. By default, all backups are kept
+    /// and the database is restored when a migration fails.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: String::from("backup"),
+            keep_last: None,
+            restore: true,
+        }
+    }
+
+    /// Prefix of the backup file names, `backup` by default. Backups with other prefixes are
+    /// ignored when removing old backups.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Keep only the last `count` backups after migrations succeed, including the new one.
+    /// With a `count` of 0, the backup is removed as soon as the migrations succeed.
+    pub fn keep_last(mut self, count: usize) -> Self {
+        self.keep_last = Some(count);
+        self
+    }
+
+    /// Don’t restore the database when a migration fails. The location of the backup is still
+    /// given in the error.
+    pub fn no_restore(mut self) -> Self {
+        self.restore = false;
+        self
+    }
+
+    /// Back up the database `schema`, returning the path of the backup
+    pub(crate) fn create(
+        &self,
+        conn: &Connection,
+        schema: &str,
+        version: usize,
+    ) -> Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let path = self.dir.join(format!(
+            "{}-{timestamp:020}-v{version}.sqlite3",
+            self.prefix
+        ));
+        conn.backup(schema, &path, None)
+            .map_err(|e| Error::with_sql(e, &format!("backup to {}", path.display())))?;
+        info!("Database backed up to {}", path.display());
+        Ok(path)
+    }
+
+    /// Restore the backup if enabled, wrapping the error of the migration
+    pub(crate) fn failed(
+        &self,
+        conn: &mut Connection,
+        schema: &str,
+        err: Error,
+        path: PathBuf,
+    ) -> Error {
+        let mut restored = false;
+        if self.restore {
+            match conn.restore(schema, &path, None::<fn(Progress)>) {
+                Ok(()) => {
+                    info!("Database restored from {}", path.display());
+                    restored = true;
+                }
+                Err(e) => error!("Failed to restore the backup {}: {e}", path.display()),
+            }
+        }
+        Error::Backup {
+            err: Box::new(err),
+            path,
+            restored,
+        }
+    }
+
+    /// Remove old backups, as configured. Failures are only logged, as migrations succeeded.
+    pub(crate) fn succeeded(&self, path: &Path) {
+        let Some(keep_last) = self.keep_last else {
+            return;
+        };
+        let mut backups = match self.list() {
+            Ok(backups) => backups,
+            Err(e) => {
+                warn!("Cannot list backups in {}: {e}", self.dir.display());
+                vec![path.to_owned()]
+            }
+        };
+        backups.sort();
+        let remove = backups.len().saturating_sub(keep_last);
+        for old in &backups[..remove] {
+            debug!("Removing old backup {}", old.display());
+            if let Err(e) = fs::remove_file(old) {
+                warn!("Cannot remove old backup {}: {e}", old.display());
+            }
+        }
+    }
+
+    /// Backups in the directory with the configured prefix
+    fn list(&self) -> std::io::Result<Vec<PathBuf>> {
+        let prefix = format!("{}-", self.prefix);
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_backup = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                n.strip_prefix(&prefix).is_some_and(|rest| {
+                    rest.len() > 20 && rest[..20].bytes().all(|b| b.is_ascii_digit())
+                }) && n.ends_with(".sqlite3")
+            });
+            if is_backup {
+                backups.push(path);
+            }
+        }
+        Ok(backups)
+    }
+}
@@ -17,12 +17,17 @@ use std::{iter::FromIterator, mem::take};
 
 use include_dir::Dir;
 
-use crate::{loader::from_directory, MigrationHook, Result, M};
+use crate::{
+    loader::{from_directory, repeatable_from_directory},
+    repeatable::Repeatable,
+    MigrationHook, Result, M,
+};
 
 /// Allows to build a `Vec<M<'u>>` with additional edits.
 #[derive(Default, Debug)]
 pub struct MigrationsBuilder<'u> {
     migrations: Vec<Option<M<'u>>>,
+    repeatable: Vec<Repeatable<'u>>,
 }
 
 impl<'u> MigrationsBuilder<'u> {
@@ -49,6 +54,7 @@ impl<'u> MigrationsBuilder<'u> {
     pub fn from_directory(dir: &'static Dir<'static>) -> Result<Self> {
         Ok(Self {
             migrations: from_directory(dir)?,
+            repeatable: repeatable_from_directory(dir)?,
         })
     }
 
@@ -66,7 +72,9 @@ impl<'u> MigrationsBuilder<'u> {
 
     /// Finalizes the builder and creates a [`crate::Migrations`].
     pub fn finalize(mut self) -> crate::Migrations<'u> {
-        self.migrations.drain(..).flatten().collect()
+        let mut migrations: crate::Migrations<'u> = self.migrations.drain(..).flatten().collect();
+        migrations.repeatable = self.repeatable;
+        migrations
     }
 }
 
@@ -74,6 +82,7 @@ impl<'u> FromIterator<M<'u>> for MigrationsBuilder<'u> {
     fn from_iter<T: IntoIterator<Item = M<'u>>>(iter: T) -> Self {
         Self {
             migrations: iter.into_iter().map(Some).collect(),
+            repeatable: Vec::new(),
         }
     }
 }
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Gather migrations registered by several crates, at link time
+
+use log::warn;
+
+use crate::{Error, MigrationDefinitionError, Migrations, Result, M};
+
+/// Migration registered with [`crate::register_migration!`]
+#[derive(Debug)]
+pub struct RegisteredMigration {
+    module: &'static str,
+    ordinal: usize,
+    migration: M<'static>,
+}
+
+impl RegisteredMigration {
+    /// Migration `migration` of the module `module`, at the position `ordinal` in the whole
+    /// set, starting at 1. Prefer the [`crate::register_migration!`] macro.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ordinal` is 0, at compile time when used in a static.
+    pub const fn new(module: &'static str, ordinal: usize, migration: M<'static>) -> Self {
+        assert!(ordinal > 0, "migration ordinals start at 1");
+        Self {
+            module,
+            ordinal,
+            migration,
+        }
+    }
+}
+
+inventory::collect!(RegisteredMigration);
+
+/// Register a migration, to be gathered with [`Migrations::collect`] along with the migrations
+/// registered by other crates linked in the program. The migration is tagged with the name of a
+/// module, typically the crate registering it, and an ordinal, its position in the whole set,
+/// starting at 1. Ordinals are what order migrations, so each one must be used exactly once.
+///
+/// The migration must be a constant expression, like `M::up("…").down("…")`.
+///
+/// # Example
+///
+/// ```
+/// use rusqlite_migration::{register_migration, Migrations, M};
+///
+/// register_migration!("core", 1, M::up("CREATE TABLE animals (name TEXT);"));
+/// // Typically, in another crate
+/// register_migration!(
+///     "food",
+///     2,
+///     M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;")
+/// );
+///
+/// let migrations = Migrations::collect().unwrap();
+/// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+/// migrations.to_latest(&mut conn).unwrap();
+/// ```
+#[macro_export]
+macro_rules! register_migration {
+    ($module:expr, $ordinal:expr, $migration:expr $(,)?) => {
+        $crate::__collect::inventory::submit! {
+            $crate::RegisteredMigration::new($module, $ordinal, $migration)
+        }
+    };
+}
+
+impl Migrations<'static> {
+    /// Gather the migrations registered with [`crate::register_migration!`] by all the crates
+    /// linked in the program, ordered by their ordinal. See the macro for an example.
+    ///
+    /// Crates not linked in the final program, because nothing else is used from them, don’t
+    /// register their migrations. The gap left in the ordinals is then reported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MigrationDefinitionError::DuplicateOrdinal`] when several migrations have the
+    /// same ordinal and [`MigrationDefinitionError::MissingOrdinal`] when ordinals are not
+    /// contiguous from 1.
+    pub fn collect() -> Result<Self> {
+        collect_registered(inventory::iter::<RegisteredMigration>)
+    }
+}
+
+/// Order registered migrations, checking their ordinals
+pub(crate) fn collect_registered<'r>(
+    registered: impl IntoIterator<Item = &'r RegisteredMigration>,
+) -> Result<Migrations<'static>> {
+    let mut registered: Vec<&RegisteredMigration> = registered.into_iter().collect();
+    // Sort by module too, so that errors are deterministic
+    registered.sort_by_key(|r| (r.ordinal, r.module));
+
+    let mut ms = Vec::with_capacity(registered.len());
+    for (i, current) in registered.iter().enumerate() {
+        if let Some(previous) = i.checked_sub(1).map(|p| registered[p]) {
+            if previous.ordinal == current.ordinal {
+                warn!(
+                    "migration ordinal {} registered by the modules “{}” and “{}”",
+                    current.ordinal, previous.module, current.module
+                );
+                return Err(Error::MigrationDefinition(
+                    MigrationDefinitionError::DuplicateOrdinal {
+                        ordinal: current.ordinal,
+                        modules: [previous.module, current.module],
+                    },
+                ));
+            }
+        }
+        if current.ordinal != i + 1 {
+            warn!(
+                "no migration registered with the ordinal {}, before the one of module “{}”",
+                i + 1,
+                current.module
+            );
+            return Err(Error::MigrationDefinition(
+                MigrationDefinitionError::MissingOrdinal { ordinal: i + 1 },
+            ));
+        }
+        ms.push(current.migration.clone());
+    }
+    Ok(Migrations::new(ms))
+}
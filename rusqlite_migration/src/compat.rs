@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Let older versions of a program use databases migrated by newer ones
+
+use rusqlite::{Connection, OptionalExtension, Transaction};
+
+use crate::{Error, Migrations, Result, SchemaVersion, M};
+
+/// Table recording the level of compatibility of the database, created only when a migration
+/// declares one with [`M::read_compatible_since`]
+pub(crate) const COMPAT_TABLE: &str = "rusqlite_migration_compat";
+
+/// Whether a program can use a database, given the migrations it knows, see
+/// [`Migrations::compatibility`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Compatibility {
+    /// The version of the database is within the migrations, which can bring it to the latest
+    /// version
+    Migratable(SchemaVersion),
+    /// The database was migrated beyond the known migrations, by a newer version of the program,
+    /// but the newer migrations declared that it can still be read by this one. The database
+    /// should not be migrated, and may be used in a degraded or read-only mode.
+    ReadCompatible {
+        /// Version of the database, [`SchemaVersion::Outside`] the migrations
+        version: SchemaVersion,
+        /// Number of migrations a program must know to read the database
+        read_compatible_since: usize,
+    },
+    /// The database was migrated beyond the known migrations and this program can’t use it
+    Incompatible {
+        /// Version of the database, [`SchemaVersion::Outside`] the migrations
+        version: SchemaVersion,
+        /// Number of migrations a program must know to read the database
+        read_compatible_since: usize,
+    },
+}
+
+impl M<'_> {
+    /// Declare that databases migrated up to this migration can still be read by programs
+    /// knowing only the first `version` migrations, that is programs that would migrate
+    /// databases up to `version`. This is typically the case of migrations adding tables,
+    /// indexes or nullable columns.
+    ///
+    /// Programs that don’t know this migration then get [`Compatibility::ReadCompatible`] from
+    /// [`Migrations::compatibility`], instead of [`Compatibility::Incompatible`]. By default, a
+    /// migration is only readable by programs knowing it. `version` is capped to that default.
+    ///
+    /// The level of compatibility of the database is the highest one of all the migrations
+    /// applied. It is stored in the database, in a `rusqlite_migration_compat` table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::M;
+    ///
+    /// // This is the third migration, programs knowing only two migrations can read the database
+    /// M::up("CREATE INDEX animals_name ON animals(name);").read_compatible_since(2);
+    /// ```
+    pub const fn read_compatible_since(mut self, version: usize) -> Self {
+        self.read_compatible_since = Some(version);
+        self
+    }
+}
+
+impl Migrations<'_> {
+    /// Tell whether the database can be used by this program, in particular when it was
+    /// migrated by a newer version of the program, with more migrations. In that case,
+    /// [`Migrations::to_latest`] fails with
+    /// [`MigrationDefinitionError::DatabaseTooFarAhead`](crate::MigrationDefinitionError::DatabaseTooFarAhead)
+    /// and [`Migrations::current_version`] returns [`SchemaVersion::Outside`], but the database
+    /// may still be readable, see [`M::read_compatible_since`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Compatibility, Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// // Newer version of the program
+    /// Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);"),
+    ///     M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;").read_compatible_since(1),
+    /// ])
+    /// .to_latest(&mut conn)
+    /// .unwrap();
+    ///
+    /// // Older version of the program
+    /// let migrations = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    /// match migrations.compatibility(&conn).unwrap() {
+    ///     Compatibility::Migratable(_) => migrations.to_latest(&mut conn).unwrap(),
+    ///     Compatibility::ReadCompatible { .. } => { /* Open the database in read-only mode */ }
+    ///     _ => panic!("the database can’t be used"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RusqliteError`] or [`Error::InvalidUserVersion`] if the version or the
+    /// level of compatibility can’t be read.
+    pub fn compatibility(&self, conn: &Connection) -> Result<Compatibility> {
+        let version = self.current_version(conn)?;
+        let SchemaVersion::Outside(v) = version else {
+            return Ok(Compatibility::Migratable(version));
+        };
+        let read_compatible_since = self
+            .read_compatible_since(conn)?
+            .unwrap_or(usize::from(v))
+            .min(usize::from(v));
+        if read_compatible_since <= self.ms.len() {
+            Ok(Compatibility::ReadCompatible {
+                version,
+                read_compatible_since,
+            })
+        } else {
+            Ok(Compatibility::Incompatible {
+                version,
+                read_compatible_since,
+            })
+        }
+    }
+
+    /// Record the level of compatibility of `version` in the database
+    pub(crate) fn write_compatibility(&self, tx: &Transaction, version: usize) -> Result<()> {
+        if !self.declares_compatibility() && !self.table_exists(tx, COMPAT_TABLE)? {
+            return Ok(());
+        }
+        let sql = self.compatibility_sql(version);
+        tx.execute_batch(&sql).map_err(|e| Error::with_sql(e, &sql))
+    }
+
+    /// Statements recording the level of compatibility of `version`. The table is removed when
+    /// no migration declares a level, so that it only exists when needed.
+    pub(crate) fn compatibility_sql(&self, version: usize) -> String {
+        let level = self.ms[..version]
+            .iter()
+            .enumerate()
+            .map(|(i, m)| m.read_compatible_since.map_or(i + 1, |v| v.min(i + 1)))
+            .max()
+            .unwrap_or(0);
+        let table = self.qualified(COMPAT_TABLE);
+        if level < version {
+            format!(
+                "CREATE TABLE IF NOT EXISTS {table}(id INTEGER PRIMARY KEY CHECK (id = 0), read_compatible_since INTEGER NOT NULL);\n\
+                INSERT OR REPLACE INTO {table}(id, read_compatible_since) VALUES (0, {level});"
+            )
+        } else {
+            format!("DROP TABLE IF EXISTS {table};")
+        }
+    }
+
+    /// Whether a migration declares a level of compatibility
+    pub(crate) fn declares_compatibility(&self) -> bool {
+        self.ms.iter().any(|m| m.read_compatible_since.is_some())
+    }
+
+    /// Level of compatibility recorded in the database, if any
+    fn read_compatible_since(&self, conn: &Connection) -> Result<Option<usize>> {
+        if !self.table_exists(conn, COMPAT_TABLE)? {
+            return Ok(None);
+        }
+        let select = format!(
+            "SELECT read_compatible_since FROM {} WHERE id = 0",
+            self.qualified(COMPAT_TABLE)
+        );
+        let level: Option<i64> = conn
+            .query_row(&select, [], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::with_sql(e, &select))?;
+        level
+            .map(|level| usize::try_from(level).map_err(|_| Error::InvalidUserVersion))
+            .transpose()
+    }
+}
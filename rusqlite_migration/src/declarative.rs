@@ -0,0 +1,412 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compute the migration going from the schema of a database to a declared target schema
+
+use std::fmt;
+
+use log::{debug, trace};
+use rusqlite::Connection;
+
+use crate::{
+    execute_statements,
+    fk_check::FKCheck,
+    rebuild::copy_statements,
+    schema::{read_raw_schema, SchemaObject, Table, TableDiff, Trigger, View},
+    sql::{parse_create_table, quote_identifier, rename_create_table, TableItem},
+    Error, Result, Schema, M,
+};
+
+/// SQL statements bringing a database to a target schema, computed by [`MigrationPlan::new`].
+///
+/// The plan can be applied directly with [`MigrationPlan::apply`] or turned into a migration
+/// with [`MigrationPlan::to_m`], so that it can be inspected, committed and replayed through
+/// [`crate::Migrations`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MigrationPlan {
+    statements: Vec<String>,
+    sql: String,
+}
+
+impl MigrationPlan {
+    /// Compute the statements to go from the schema of `conn` to the schema declared by
+    /// `target_sql`, as a list of `CREATE` statements.
+    ///
+    /// Tables are altered with `ALTER TABLE … ADD COLUMN` and `ALTER TABLE … DROP COLUMN` when
+    /// possible. Otherwise, they are rebuilt following the [generalized `ALTER TABLE`
+    /// procedure][procedure] of SQLite: a new table is created, the columns shared by the old and
+    /// new definitions are copied, the old table is dropped and the new one is renamed. Indexes,
+    /// triggers and views are dropped and recreated as needed.
+    ///
+    /// The plan is checked against a copy of the schema of `conn` before being returned.
+    ///
+    /// # Data loss
+    ///
+    /// Renamed tables or columns can’t be told apart from removed and added ones: the data they
+    /// hold is lost when the plan is applied. Please review the plan and write such migrations by
+    /// hand.
+    ///
+    /// # Foreign keys
+    ///
+    /// Rebuilding a table drops it, which can violate foreign key constraints. As advised in
+    /// [`M::foreign_key_check`], disable `PRAGMA foreign_keys` while applying the plan.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::MigrationPlan;
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute_batch("CREATE TABLE animals (name TEXT); INSERT INTO animals VALUES ('dog');")
+    ///     .unwrap();
+    ///
+    /// let plan = MigrationPlan::new(
+    ///     &conn,
+    ///     "CREATE TABLE animals (name TEXT NOT NULL, legs INTEGER NOT NULL DEFAULT 4);",
+    /// )
+    /// .unwrap();
+    /// // Review the SQL, or save it to use it with `M::up`
+    /// println!("{plan}");
+    ///
+    /// plan.apply(&mut conn).unwrap();
+    /// let legs: i64 = conn
+    ///     .query_row("SELECT legs FROM animals WHERE name = 'dog'", [], |row| row.get(0))
+    ///     .unwrap();
+    /// assert_eq!(legs, 4);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RusqliteError`] if `target_sql` or the schema of `conn` is invalid, or
+    /// [`Error::TargetSchemaNotReached`] if the plan does not lead to the target schema, for
+    /// instance because it contains constructs not supported by this function.
+    ///
+    /// [procedure]: https://www.sqlite.org/lang_altertable.html#otheralter
+    pub fn new(conn: &Connection, target_sql: &str) -> Result<Self> {
+        let target_conn = Connection::open_in_memory()?;
+        target_conn
+            .execute_batch(target_sql)
+            .map_err(|e| Error::with_sql(e, target_sql))?;
+
+        let current_objects = read_raw_schema(conn)?;
+        let plan = Planner {
+            current: Schema::read(conn)?,
+            current_objects: &current_objects,
+            target: Schema::read(&target_conn)?,
+            target_objects: &read_raw_schema(&target_conn)?,
+        }
+        .plan();
+
+        // Make sure the plan actually reaches the target schema
+        let check_conn = schema_copy(&current_objects)?;
+        check_conn
+            .execute_batch(&plan.sql)
+            .map_err(|e| Error::with_sql(e, &plan.sql))?;
+        let diff = Schema::read(&check_conn)?.diff(&Schema::read(&target_conn)?);
+        if !diff.is_empty() {
+            return Err(Error::TargetSchemaNotReached(Box::new(diff)));
+        }
+
+        Ok(plan)
+    }
+
+    /// The statements of the plan
+    pub fn statements(&self) -> &[String] {
+        &self.statements
+    }
+
+    /// The SQL text of the plan, all statements included
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Whether the database already has the target schema
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Migration running the plan, with [`M::foreign_key_check`] enabled
+    pub fn to_m(&self) -> M<'_> {
+        M::up(&self.sql).foreign_key_check()
+    }
+
+    /// Apply the plan in a transaction, checking foreign keys before committing. The
+    /// `user_version` is not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Statement`] if a statement fails and [`Error::ForeignKeyCheck`] if the
+    /// foreign key check fails. The transaction is rolled back in both cases.
+    pub fn apply(&self, conn: &mut Connection) -> Result<()> {
+        let tx = conn.transaction()?;
+        {
+            debug!("Running: {}", self.sql);
+            execute_statements(&tx, &self.sql)?;
+            FKCheck::new().validate(&tx)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sql)
+    }
+}
+
+impl MigrationPlan {
+    fn from_statements(statements: Vec<String>) -> Self {
+        let sql = statements.iter().map(|s| format!("{s};\n")).collect();
+        Self { statements, sql }
+    }
+}
+
+/// Create an in-memory database with the same schema as the objects given
+fn schema_copy(objects: &[SchemaObject]) -> Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    for sql in objects.iter().filter_map(|o| o.sql.as_deref()) {
+        conn.execute_batch(sql)
+            .map_err(|e| Error::with_sql(e, sql))?;
+    }
+    Ok(conn)
+}
+
+struct Planner<'a> {
+    current: Schema,
+    current_objects: &'a [SchemaObject],
+    target: Schema,
+    target_objects: &'a [SchemaObject],
+}
+
+/// How a table present in both schemas is changed
+enum TableChange<'a> {
+    Alter(&'a TableDiff, Vec<String>),
+    Rebuild(&'a TableDiff),
+}
+
+impl Planner<'_> {
+    fn plan(&self) -> MigrationPlan {
+        let diff = self.current.diff(&self.target);
+        let mut stmts = Vec::new();
+        if diff.is_empty() {
+            return MigrationPlan::from_statements(stmts);
+        }
+
+        let changes: Vec<TableChange> = diff
+            .changed_tables
+            .iter()
+            .map(|table_diff| match self.alter_statements(table_diff) {
+                Some(alter) => TableChange::Alter(table_diff, alter),
+                None => TableChange::Rebuild(table_diff),
+            })
+            .collect();
+
+        // Views and triggers may reference tables being removed or rebuilt, and renaming a table
+        // fails when a view or a trigger is referencing a missing table. Recreate all of them in
+        // that case.
+        let recreate_all = !diff.tables.removed.is_empty()
+            || changes.iter().any(|c| matches!(c, TableChange::Rebuild(_)));
+        let names = |views: &[&View], triggers: &[&Trigger], kind: &str, name: &str| {
+            recreate_all
+                || match kind {
+                    "view" => views.iter().any(|v| v.name == name),
+                    _ => triggers.iter().any(|t| t.name == name),
+                }
+        };
+        let (views, triggers) = (&diff.views, &diff.triggers);
+        let dropped_views: Vec<&View> = (views.removed.iter())
+            .chain(views.changed.iter().map(|(old, _)| old))
+            .collect();
+        let dropped_triggers: Vec<&Trigger> = (triggers.removed.iter())
+            .chain(triggers.changed.iter().map(|(old, _)| old))
+            .collect();
+        let created_views: Vec<&View> = (views.added.iter())
+            .chain(views.changed.iter().map(|(_, new)| new))
+            .collect();
+        let created_triggers: Vec<&Trigger> = (triggers.added.iter())
+            .chain(triggers.changed.iter().map(|(_, new)| new))
+            .collect();
+        let dropped = |kind: &str, name: &str| names(&dropped_views, &dropped_triggers, kind, name);
+        let created = |kind: &str, name: &str| names(&created_views, &created_triggers, kind, name);
+
+        for kind in ["trigger", "view"] {
+            for object in self.current_objects.iter().rev() {
+                if object.kind == kind && dropped(kind, &object.name) {
+                    stmts.push(format!(
+                        "DROP {} {}",
+                        kind.to_uppercase(),
+                        quote_identifier(&object.name)
+                    ));
+                }
+            }
+        }
+
+        for change in &changes {
+            if let TableChange::Alter(table_diff, _) = change {
+                let indexes = &table_diff.indexes;
+                for index in indexes
+                    .removed
+                    .iter()
+                    .chain(indexes.changed.iter().map(|(i, _)| i))
+                {
+                    if index.sql.is_some() {
+                        stmts.push(format!("DROP INDEX {}", quote_identifier(&index.name)));
+                    }
+                }
+            }
+        }
+
+        for table in &diff.tables.removed {
+            stmts.push(format!("DROP TABLE {}", quote_identifier(&table.name)));
+        }
+
+        for table in &diff.tables.added {
+            stmts.push(self.target_sql("table", &table.name));
+            stmts.extend(self.target_indexes(&table.name, |_| true));
+        }
+
+        for change in changes {
+            match change {
+                TableChange::Alter(table_diff, alter) => {
+                    stmts.extend(alter);
+                    let indexes = &table_diff.indexes;
+                    stmts.extend(self.target_indexes(&table_diff.name, |name| {
+                        (indexes.added.iter())
+                            .chain(indexes.changed.iter().map(|(_, i)| i))
+                            .any(|i| i.name == name)
+                    }));
+                }
+                TableChange::Rebuild(table_diff) => match self.rebuild_statements(table_diff) {
+                    Some(rebuild) => {
+                        stmts.extend(rebuild);
+                        stmts.extend(self.target_indexes(&table_diff.name, |_| true));
+                    }
+                    // Left as is, the final check reports the table as not reaching the target
+                    None => debug!("table {} is not supported, skipping it", table_diff.name),
+                },
+            }
+        }
+
+        for kind in ["view", "trigger"] {
+            for object in self.target_objects {
+                if object.kind == kind && created(kind, &object.name) {
+                    stmts.push(self.target_sql(kind, &object.name));
+                }
+            }
+        }
+
+        trace!("planned statements: {stmts:?}");
+        MigrationPlan::from_statements(stmts)
+    }
+
+    fn target_sql(&self, kind: &str, name: &str) -> String {
+        find_sql(self.target_objects, kind, name).to_owned()
+    }
+
+    /// `CREATE INDEX` statements of the target, for a given table
+    fn target_indexes<'p>(
+        &'p self,
+        table: &'p str,
+        filter: impl Fn(&str) -> bool + 'p,
+    ) -> impl Iterator<Item = String> + 'p {
+        self.target_objects
+            .iter()
+            .filter(move |o| o.kind == "index" && o.table == table && filter(&o.name))
+            .filter_map(|o| o.sql.clone())
+    }
+
+    fn table<'s>(schema: &'s Schema, name: &str) -> Option<&'s Table> {
+        schema.tables.iter().find(|t| t.name == name)
+    }
+
+    /// Statements to change a table with `ALTER TABLE`, or `None` if a rebuild is required.
+    /// Candidate statements are tried on a copy of the table.
+    fn alter_statements(&self, table_diff: &TableDiff) -> Option<Vec<String>> {
+        if !table_diff.columns.changed.is_empty() {
+            return None;
+        }
+        let name = &table_diff.name;
+        let target_table = Self::table(&self.target, name)?;
+        let target_sql = find_sql(self.target_objects, "table", name);
+        let create = parse_create_table(target_sql)?;
+
+        let quoted_name = quote_identifier(name);
+        let mut stmts = Vec::new();
+        for column in &table_diff.columns.removed {
+            stmts.push(format!(
+                "ALTER TABLE {quoted_name} DROP COLUMN {}",
+                quote_identifier(&column.name)
+            ));
+        }
+        for column in &table_diff.columns.added {
+            let definition = create.items.iter().find_map(|item| match item {
+                TableItem::Column { name, definition } if *name == column.name => Some(definition),
+                _ => None,
+            })?;
+            stmts.push(format!("ALTER TABLE {quoted_name} ADD COLUMN {definition}"));
+        }
+
+        let scratch = Connection::open_in_memory().ok()?;
+        scratch
+            .execute_batch(find_sql(self.current_objects, "table", name))
+            .ok()?;
+        for stmt in &stmts {
+            if let Err(e) = scratch.execute_batch(stmt) {
+                debug!("table {name} needs to be rebuilt, {stmt} failed: {e}");
+                return None;
+            }
+        }
+        let altered = Schema::read(&scratch).ok()?;
+        let altered = Self::table(&altered, name)?;
+        let same = altered.sql == target_table.sql
+            && altered.columns == target_table.columns
+            && altered.foreign_keys == target_table.foreign_keys;
+        if !same {
+            debug!("table {name} needs to be rebuilt, altering it does not give the target");
+        }
+        same.then_some(stmts)
+    }
+
+    /// Statements of the generalized `ALTER TABLE` procedure, except for indexes, or `None` if the
+    /// definition of the target table could not be parsed
+    fn rebuild_statements(&self, table_diff: &TableDiff) -> Option<Vec<String>> {
+        let name = &table_diff.name;
+        let new_name = format!("rusqlite_migration_new_{name}");
+        let target_sql = self.target_sql("table", name);
+        let create_new = rename_create_table(&target_sql, &new_name)?;
+
+        let current_table = Self::table(&self.current, name)?;
+        let target_table = Self::table(&self.target, name)?;
+        let shared_columns: Vec<String> = target_table
+            .columns
+            .iter()
+            .filter(|c| current_table.columns.iter().any(|o| o.name == c.name))
+            .map(|c| quote_identifier(&c.name))
+            .collect();
+
+        let mut stmts = vec![create_new];
+        stmts.extend(copy_statements(name, &new_name, &shared_columns));
+        Some(stmts)
+    }
+}
+
+fn find_sql<'o>(objects: &'o [SchemaObject], kind: &str, name: &str) -> &'o str {
+    objects
+        .iter()
+        .find(|o| o.kind == kind && o.name == name)
+        .and_then(|o| o.sql.as_deref())
+        .unwrap_or_default()
+}
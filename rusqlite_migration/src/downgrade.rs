@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Policy restricting downgrades
+
+use crate::{Error, Migrations, Result, M};
+
+/// Which downgrades [`Migrations::to_version`] may run, see [`Migrations::downgrade_policy`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DowngradePolicy {
+    /// Run any down migration
+    #[default]
+    Allow,
+    /// Don’t run any down migration
+    Deny,
+    /// Only run down migrations of migrations marked with [`M::non_destructive`]
+    NonDestructiveOnly,
+}
+
+impl M<'_> {
+    /// Mark the down migration as non-destructive: reverting the migration doesn’t lose data.
+    /// Only such migrations can be reverted with [`DowngradePolicy::NonDestructiveOnly`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::M;
+    ///
+    /// M::up("CREATE INDEX animals_name ON animals(name);")
+    ///     .down("DROP INDEX animals_name;")
+    ///     .non_destructive();
+    /// ```
+    pub const fn non_destructive(mut self) -> Self {
+        self.non_destructive = true;
+        self
+    }
+}
+
+impl Migrations<'_> {
+    /// Restrict the downgrades run by [`Migrations::to_version`], to prevent a production
+    /// build from destroying data by rolling back. Downgrades are allowed by default.
+    ///
+    /// Denied downgrades fail with [`Error::DowngradeDenied`] before any transaction is
+    /// started, and before any backup is made.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{DowngradePolicy, Error, Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    ///     M::up("CREATE INDEX animals_name ON animals(name);")
+    ///         .down("DROP INDEX animals_name;")
+    ///         .non_destructive(),
+    /// ])
+    /// .downgrade_policy(DowngradePolicy::NonDestructiveOnly);
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// migrations.to_latest(&mut conn).unwrap();
+    /// migrations.to_version(&mut conn, 1).unwrap();
+    /// assert!(matches!(
+    ///     migrations.to_version(&mut conn, 0),
+    ///     Err(Error::DowngradeDenied { migration_index: 0, .. })
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn downgrade_policy(mut self, policy: DowngradePolicy) -> Self {
+        self.downgrade_policy = policy;
+        self
+    }
+
+    /// Check that the policy allows to revert the migrations from `current_version` down to
+    /// `target_version`
+    pub(crate) fn check_downgrade(
+        &self,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        if current_version > self.ms.len() && !self.store_down {
+            // Reported as MigrationDefinitionError::DatabaseTooFarAhead
+            return Ok(());
+        }
+        // Stored down migrations, beyond the known ones, are considered destructive
+        let denied =
+            (target_version..current_version)
+                .rev()
+                .find(|&i| match self.downgrade_policy {
+                    DowngradePolicy::Allow => false,
+                    DowngradePolicy::Deny => true,
+                    DowngradePolicy::NonDestructiveOnly => {
+                        !self.ms.get(i).is_some_and(|m| m.non_destructive)
+                    }
+                });
+        match denied {
+            Some(migration_index) => Err(Error::DowngradeDenied {
+                migration_index,
+                policy: self.downgrade_policy,
+            }),
+            None => Ok(()),
+        }
+    }
+}
@@ -17,7 +17,7 @@
 
 use std::fmt;
 
-use crate::SchemaVersion;
+use crate::{DowngradePolicy, Lint, SchemaDiff, SchemaVersion};
 
 /// A typedef of the result returned by many methods.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -30,6 +30,7 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 /// minor or patch versions. These messages are meant to be consumed only by humans, not used
 /// for flow control in the program.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(clippy::enum_variant_names)]
 #[non_exhaustive]
 pub enum Error {
@@ -38,6 +39,7 @@ pub enum Error {
         /// SQL query that caused the error
         query: String,
         /// Error returned by rusqlite
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::manifest::display"))]
         err: rusqlite::Error,
     },
     /// Error with the specified schema version
@@ -53,9 +55,93 @@ pub enum Error {
     Hook(String),
     /// Error returned when loading migrations from directory
     FileLoad(String),
+    /// The statements generated to reach a target schema don’t lead to that schema. The
+    /// remaining differences are given.
+    TargetSchemaNotReached(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::manifest::display"))]
+        Box<SchemaDiff>,
+    ),
+    /// A migration failed while being applied or reverted. `err` is the underlying error: SQL
+    /// error, foreign key check or hook error.
+    Migration {
+        /// Index of the migration that failed
+        migration_index: usize,
+        /// Whether the migration was applied or reverted
+        direction: MigrationDirection,
+        /// Version the database was being migrated to
+        target_version: usize,
+        /// Comment of the migration, see [`crate::M::comment`]
+        comment: Option<String>,
+        /// Error returned by the migration
+        err: Box<Error>,
+    },
+    /// A statement of a migration failed. The location is given within the migration text, which
+    /// is the content of the `up.sql` or `down.sql` file for migrations loaded from a directory.
+    Statement {
+        /// Statement that failed
+        statement: String,
+        /// Offset in bytes of the statement in the migration text
+        offset: usize,
+        /// Line of the statement in the migration text, starting at 1
+        line: usize,
+        /// Column of the statement in the migration text, in characters and starting at 1
+        column: usize,
+        /// Error returned by rusqlite
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::manifest::display"))]
+        err: rusqlite::Error,
+    },
+    /// Lints denied with [`crate::Migrations::lint_level`] were found by
+    /// [`crate::Migrations::validate`]
+    Lint(Vec<Lint>),
+    /// Reverting a migration is not allowed by the policy set with
+    /// [`crate::Migrations::downgrade_policy`]. Nothing was changed in the database.
+    DowngradeDenied {
+        /// Index of the first migration that would have been reverted and is not allowed to
+        migration_index: usize,
+        /// Policy denying the downgrade
+        policy: DowngradePolicy,
+    },
+    /// The migration applied at `version` is not the one defined at that version anymore,
+    /// typically because a module inserted migrations instead of appending them, see
+    /// [`crate::Migrations::module`], or because a migration with an id was deleted or moved, see
+    /// [`crate::M::id`]. Nothing was changed in the database.
+    MigrationsReordered {
+        /// Version of the migration, starting at 1
+        version: usize,
+        /// Description of the migration applied
+        applied: String,
+        /// Description of the migration now defined at that version
+        defined: String,
+    },
+    /// No migration has this id, see [`crate::M::id`]
+    UnknownMigrationId(String),
+    /// A repeatable migration failed, see [`crate::Migrations::repeatable`]
+    Repeatable {
+        /// Name of the repeatable migration
+        name: String,
+        /// Error returned by the migration
+        err: Box<Error>,
+    },
+    /// Migrations failed after a backup of the database was made, see [`crate::Backup`]
+    #[cfg(feature = "backup")]
+    Backup {
+        /// Error that made the migrations fail
+        err: Box<Error>,
+        /// Location of the backup
+        path: std::path::PathBuf,
+        /// Whether the database was restored from the backup
+        restored: bool,
+    },
+    /// A migration manifest can’t be turned into migrations, see
+    /// [`crate::Migrations::from_manifest`]
+    #[cfg(feature = "serde")]
+    Manifest(String),
     /// An unknown error occurred. *Note*: such errors are not comparable between one another,
     /// much like NaN for floats.
-    Unrecognized(Box<dyn std::error::Error + Send + Sync + 'static>),
+    Unrecognized(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::manifest::display"))]
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    ),
 }
 
 impl PartialEq for Error {
@@ -68,7 +154,81 @@ impl PartialEq for Error {
             (Self::SpecifiedSchemaVersion(a), Self::SpecifiedSchemaVersion(b)) => a == b,
             (Self::MigrationDefinition(a), Self::MigrationDefinition(b)) => a == b,
             (Self::ForeignKeyCheck(e1), Self::ForeignKeyCheck(e2)) => e1 == e2,
-            (Self::Hook(a), Self::Hook(b)) | (Self::FileLoad(a), Self::FileLoad(b)) => a == b,
+            (Self::Lint(l1), Self::Lint(l2)) => l1 == l2,
+            (
+                Self::DowngradeDenied {
+                    migration_index: i1,
+                    policy: p1,
+                },
+                Self::DowngradeDenied {
+                    migration_index: i2,
+                    policy: p2,
+                },
+            ) => i1 == i2 && p1 == p2,
+            (Self::TargetSchemaNotReached(a), Self::TargetSchemaNotReached(b)) => a == b,
+            (Self::Repeatable { name: n1, err: e1 }, Self::Repeatable { name: n2, err: e2 }) => {
+                n1 == n2 && e1 == e2
+            }
+            (
+                Self::MigrationsReordered {
+                    version: v1,
+                    applied: a1,
+                    defined: d1,
+                },
+                Self::MigrationsReordered {
+                    version: v2,
+                    applied: a2,
+                    defined: d2,
+                },
+            ) => v1 == v2 && a1 == a2 && d1 == d2,
+            (
+                Self::Migration {
+                    migration_index: i1,
+                    direction: d1,
+                    target_version: t1,
+                    comment: c1,
+                    err: e1,
+                },
+                Self::Migration {
+                    migration_index: i2,
+                    direction: d2,
+                    target_version: t2,
+                    comment: c2,
+                    err: e2,
+                },
+            ) => i1 == i2 && d1 == d2 && t1 == t2 && c1 == c2 && e1 == e2,
+            (
+                Self::Statement {
+                    statement: s1,
+                    offset: o1,
+                    err: e1,
+                    ..
+                },
+                Self::Statement {
+                    statement: s2,
+                    offset: o2,
+                    err: e2,
+                    ..
+                },
+            ) => s1 == s2 && o1 == o2 && e1 == e2,
+            #[cfg(feature = "backup")]
+            (
+                Self::Backup {
+                    err: e1,
+                    path: p1,
+                    restored: r1,
+                },
+                Self::Backup {
+                    err: e2,
+                    path: p2,
+                    restored: r2,
+                },
+            ) => e1 == e2 && p1 == p2 && r1 == r2,
+            (Self::Hook(a), Self::Hook(b))
+            | (Self::FileLoad(a), Self::FileLoad(b))
+            | (Self::UnknownMigrationId(a), Self::UnknownMigrationId(b)) => a == b,
+            #[cfg(feature = "serde")]
+            (Self::Manifest(a), Self::Manifest(b)) => a == b,
             // This makes Unrecognized errors behave like NaN (where NaN != NaN)
             (Self::Unrecognized(_), Self::Unrecognized(_)) => false,
             // Fallback to comparing enum variants
@@ -86,6 +246,39 @@ impl Error {
             err: e,
         }
     }
+
+    /// Locate the statement that failed in the migration text `sql`
+    pub(crate) fn in_statement(
+        err: rusqlite::Error,
+        statement: &crate::sql::Statement<'_>,
+        sql: &str,
+    ) -> Error {
+        let (line, column) = crate::sql::line_column(sql, statement.start);
+        Error::Statement {
+            statement: String::from(statement.text),
+            offset: statement.start,
+            line,
+            column,
+            err,
+        }
+    }
+
+    /// Add the context of the migration that failed
+    pub(crate) fn in_migration(
+        self,
+        migration_index: usize,
+        direction: MigrationDirection,
+        target_version: usize,
+        comment: Option<&str>,
+    ) -> Error {
+        Error::Migration {
+            migration_index,
+            direction,
+            target_version,
+            comment: comment.map(str::to_owned),
+            err: Box::new(self),
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -126,6 +319,82 @@ impl fmt::Display for Error {
             Error::InvalidUserVersion => {
                 write!(f, "rusqlite_migration error: invalid user version received")
             }
+            Error::TargetSchemaNotReached(diff) => write!(
+                f,
+                "rusqlite_migration error: the target schema is not reached, remaining differences:\n{diff}"
+            ),
+            Error::Migration {
+                migration_index,
+                direction,
+                target_version,
+                comment,
+                err,
+            } => {
+                write!(f, "rusqlite_migration error in migration {migration_index}")?;
+                if let Some(comment) = comment {
+                    write!(f, " ({comment})")?;
+                }
+                write!(
+                    f,
+                    ", migrating {direction} to version {target_version}: {err}"
+                )
+            }
+            Error::Statement {
+                statement,
+                offset,
+                line,
+                column,
+                err,
+            } => write!(
+                f,
+                "rusqlite_migration error in statement '{statement}' at line {line}, column {column} (byte {offset}): {err}"
+            ),
+            Error::Lint(lints) => {
+                writeln!(f, "rusqlite_migration error: denied lints found in migrations:")?;
+                for lint in lints {
+                    writeln!(f, "  - {lint}")?;
+                }
+                Ok(())
+            }
+            Error::DowngradeDenied {
+                migration_index,
+                policy,
+            } => write!(
+                f,
+                "rusqlite_migration error: reverting migration {migration_index} is denied by the downgrade policy {policy:?}"
+            ),
+            Error::MigrationsReordered {
+                version,
+                applied,
+                defined,
+            } => write!(
+                f,
+                "rusqlite_migration error: {applied} was applied at version {version}, but {defined} is now defined at that version. Migrations must only be appended."
+            ),
+            Error::Repeatable { name, err } => {
+                write!(f, "rusqlite_migration error: repeatable migration “{name}” failed: {err}")
+            }
+            Error::UnknownMigrationId(id) => {
+                write!(f, "rusqlite_migration error: no migration has the id “{id}”")
+            }
+            #[cfg(feature = "backup")]
+            Error::Backup {
+                err,
+                path,
+                restored: true,
+            } => write!(
+                f,
+                "{err} (the database was restored from the backup {})",
+                path.display()
+            ),
+            #[cfg(feature = "backup")]
+            Error::Backup {
+                err,
+                path,
+                restored: false,
+            } => write!(f, "{err} (a backup of the database is at {})", path.display()),
+            #[cfg(feature = "serde")]
+            Error::Manifest(e) => write!(f, "rusqlite_migration error in migration manifest: {e}"),
         }
     }
 }
@@ -137,8 +406,21 @@ impl std::error::Error for Error {
             Error::SpecifiedSchemaVersion(e) => Some(e),
             Error::MigrationDefinition(e) => Some(e),
             Error::ForeignKeyCheck(vec) => Some(vec.first()?),
+            Error::Lint(lints) => Some(lints.first()?),
             Error::Unrecognized(ref e) => Some(&**e),
-            Error::Hook(_) | Error::FileLoad(_) | Error::InvalidUserVersion => None,
+            Error::Migration { err, .. } | Error::Repeatable { err, .. } => Some(&**err),
+            Error::Statement { err, .. } => Some(err),
+            #[cfg(feature = "backup")]
+            Error::Backup { err, .. } => Some(&**err),
+            #[cfg(feature = "serde")]
+            Error::Manifest(_) => None,
+            Error::Hook(_)
+            | Error::FileLoad(_)
+            | Error::InvalidUserVersion
+            | Error::DowngradeDenied { .. }
+            | Error::MigrationsReordered { .. }
+            | Error::UnknownMigrationId(_)
+            | Error::TargetSchemaNotReached(_) => None,
         }
     }
 }
@@ -152,8 +434,28 @@ impl From<rusqlite::Error> for Error {
     }
 }
 
+/// Direction of a migration
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MigrationDirection {
+    /// The migration is applied, running [`crate::M::up`]
+    Up,
+    /// The migration is reverted, running [`crate::M::down`]
+    Down,
+}
+
+impl fmt::Display for MigrationDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationDirection::Up => write!(f, "up"),
+            MigrationDirection::Down => write!(f, "down"),
+        }
+    }
+}
+
 /// Errors related to schema versions
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(clippy::enum_variant_names)]
 #[non_exhaustive]
 pub enum SchemaVersionError {
@@ -185,6 +487,7 @@ impl std::error::Error for SchemaVersionError {}
 
 /// Errors related to schema versions
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(clippy::enum_variant_names)]
 #[non_exhaustive]
 pub enum MigrationDefinitionError {
@@ -195,8 +498,52 @@ pub enum MigrationDefinitionError {
     },
     /// Attempt to migrate when no migrations are defined
     NoMigrationsDefined,
-    /// Attempt to migrate when the database is currently at a higher migration level (see <https://github.com/cljoly/rusqlite_migration/issues/17>)
+    /// Attempt to migrate when the database is currently at a higher migration level (see <https://github.com/cljoly/rusqlite_migration/issues/17>).
+    /// See also [`crate::Migrations::store_down_migrations`] and
+    /// [`crate::Migrations::compatibility`].
     DatabaseTooFarAhead,
+    /// The down step of the migration does not restore the schema that was in place before the
+    /// up step
+    SchemaNotRestored {
+        /// Index of the migration that caused the error
+        migration_index: usize,
+    },
+    /// Several migrations were registered with the same ordinal, see
+    /// [`crate::Migrations::collect`]
+    DuplicateOrdinal {
+        /// Ordinal registered more than once
+        ordinal: usize,
+        /// Modules of two of the migrations registered with that ordinal
+        modules: [&'static str; 2],
+    },
+    /// No migration was registered with an ordinal, while migrations were registered with higher
+    /// ones, see [`crate::Migrations::collect`]
+    MissingOrdinal {
+        /// First missing ordinal
+        ordinal: usize,
+    },
+    /// Several migrations have the same id, see [`crate::M::id`]
+    DuplicateId {
+        /// Indices of two of the migrations with that id
+        migration_indices: [usize; 2],
+    },
+    /// A migration has no id, while some migrations declare dependencies, see
+    /// [`crate::M::depends_on`]
+    MissingId {
+        /// Index of the migration that caused the error
+        migration_index: usize,
+    },
+    /// A migration depends on an id no migration has, see [`crate::M::depends_on`]
+    UnknownDependency {
+        /// Index of the migration that caused the error
+        migration_index: usize,
+    },
+    /// The dependencies of a migration form a cycle, so it can’t be ordered, see
+    /// [`crate::M::depends_on`]
+    DependencyCycle {
+        /// Index of the first migration that couldn’t be ordered
+        migration_index: usize,
+    },
 }
 
 impl fmt::Display for MigrationDefinitionError {
@@ -220,6 +567,41 @@ impl fmt::Display for MigrationDefinitionError {
                     "Attempt to migrate a database with a migration number that is too high"
                 )
             }
+            MigrationDefinitionError::SchemaNotRestored { migration_index } => {
+                write!(
+                    f,
+                    "Migration {} (version {} -> {}) is not reverted by its down migration",
+                    migration_index,
+                    migration_index,
+                    migration_index + 1
+                )
+            }
+            MigrationDefinitionError::DuplicateOrdinal {
+                ordinal,
+                modules: [first, second],
+            } => write!(
+                f,
+                "Migrations of the modules “{first}” and “{second}” are both registered with the ordinal {ordinal}"
+            ),
+            MigrationDefinitionError::MissingOrdinal { ordinal } => write!(
+                f,
+                "No migration is registered with the ordinal {ordinal}, but some are with higher ordinals"
+            ),
+            MigrationDefinitionError::DuplicateId {
+                migration_indices: [first, second],
+            } => write!(f, "Migrations {first} and {second} have the same id"),
+            MigrationDefinitionError::MissingId { migration_index } => write!(
+                f,
+                "Migration {migration_index} has no id, but some migrations declare dependencies"
+            ),
+            MigrationDefinitionError::UnknownDependency { migration_index } => write!(
+                f,
+                "Migration {migration_index} depends on an id that no migration has"
+            ),
+            MigrationDefinitionError::DependencyCycle { migration_index } => write!(
+                f,
+                "Migration {migration_index} can’t be ordered, as its dependencies form a cycle"
+            ),
         }
     }
 }
@@ -228,6 +610,7 @@ impl std::error::Error for MigrationDefinitionError {}
 
 /// Error caused by a foreign key check
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ForeignKeyCheckError {
     pub(super) table: String,
     pub(super) rowid: i64,
@@ -250,13 +633,19 @@ impl std::error::Error for ForeignKeyCheckError {}
 
 /// Error enum with rusqlite or hook-specified errors.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(clippy::enum_variant_names)]
 #[non_exhaustive]
 pub enum HookError {
     /// Rusqlite error, query may indicate the attempted SQL query
-    RusqliteError(rusqlite::Error),
+    RusqliteError(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::manifest::display"))]
+        rusqlite::Error,
+    ),
     /// Error returned by the hook
     Hook(String),
+    /// The foreign key check run by the hook failed, see [`crate::rebuild_table`]
+    ForeignKeyCheck(Vec<ForeignKeyCheckError>),
 }
 
 impl From<rusqlite::Error> for HookError {
@@ -270,6 +659,7 @@ impl From<HookError> for Error {
         match e {
             HookError::RusqliteError(err) => Error::with_sql(err, ""),
             HookError::Hook(s) => Error::Hook(s),
+            HookError::ForeignKeyCheck(e) => Error::ForeignKeyCheck(e),
         }
     }
 }
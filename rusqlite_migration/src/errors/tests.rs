@@ -65,6 +65,54 @@ fn all_errors() -> Vec<(&'static str, crate::Error)> {
                 },
             ]),
         ),
+        (
+            "statement",
+            Statement {
+                statement: "SELECT * FROM table42;".to_owned(),
+                offset: 12,
+                line: 2,
+                column: 3,
+                err: rusqlite::Error::InvalidQuery,
+            },
+        ),
+        (
+            "lint",
+            Lint(vec![crate::Lint {
+                migration_index: 1,
+                direction: crate::MigrationDirection::Up,
+                rule: crate::LintRule::Vacuum,
+                level: crate::LintLevel::Deny,
+                statement: "VACUUM;".to_owned(),
+                line: 1,
+                column: 1,
+            }]),
+        ),
+        (
+            "downgrade_denied",
+            DowngradeDenied {
+                migration_index: 2,
+                policy: crate::DowngradePolicy::NonDestructiveOnly,
+            },
+        ),
+        (
+            "migrations_reordered",
+            MigrationsReordered {
+                version: 2,
+                applied: "migration 1 of module “food” (checksum 0123456789abcdef)".to_owned(),
+                defined: "migration 1 of module “food” (checksum fedcba9876543210)".to_owned(),
+            },
+        ),
+        (
+            "unknown_migration_id",
+            UnknownMigrationId("20240101_animals".to_owned()),
+        ),
+        (
+            "repeatable",
+            Repeatable {
+                name: "animal_names".to_owned(),
+                err: Box::new(Hook("error inside hook".to_owned())),
+            },
+        ),
         ("hook", Hook("error inside hook".to_owned())),
         ("file_load", FileLoad("file causing problem".to_owned())),
         (
@@ -196,6 +244,19 @@ fn test_hook_conversion_msg() {
     assert_eq!(Error::from(hook_error), Error::Hook(msg))
 }
 
+#[test]
+fn test_hook_conversion_fk_check() {
+    let fk_errors = vec![ForeignKeyCheckError {
+        table: "t1".to_owned(),
+        rowid: 1,
+        parent: "t2".to_owned(),
+        fkid: 0,
+    }];
+    let hook_error = HookError::ForeignKeyCheck(fk_errors.clone());
+
+    assert_eq!(Error::from(hook_error), Error::ForeignKeyCheck(fk_errors))
+}
+
 #[test]
 fn test_schema_version_error_display() {
     let err = SchemaVersionError::TargetVersionOutOfRange {
@@ -234,6 +295,44 @@ fn test_migration_definition_error_display() {
     assert_eq!(
         "Attempt to migrate with no migrations defined",
         format!("{err}")
+    );
+
+    let err = MigrationDefinitionError::DuplicateOrdinal {
+        ordinal: 3,
+        modules: ["core", "food"],
+    };
+    assert_eq!(
+        "Migrations of the modules “core” and “food” are both registered with the ordinal 3",
+        format!("{err}")
+    );
+
+    let err = MigrationDefinitionError::MissingOrdinal { ordinal: 2 };
+    assert_eq!(
+        "No migration is registered with the ordinal 2, but some are with higher ordinals",
+        format!("{err}")
+    );
+
+    let err = MigrationDefinitionError::DuplicateId {
+        migration_indices: [0, 2],
+    };
+    assert_eq!("Migrations 0 and 2 have the same id", format!("{err}"));
+
+    let err = MigrationDefinitionError::MissingId { migration_index: 1 };
+    assert_eq!(
+        "Migration 1 has no id, but some migrations declare dependencies",
+        format!("{err}")
+    );
+
+    let err = MigrationDefinitionError::UnknownDependency { migration_index: 1 };
+    assert_eq!(
+        "Migration 1 depends on an id that no migration has",
+        format!("{err}")
+    );
+
+    let err = MigrationDefinitionError::DependencyCycle { migration_index: 1 };
+    assert_eq!(
+        "Migration 1 can’t be ordered, as its dependencies form a cycle",
+        format!("{err}")
     )
 }
 
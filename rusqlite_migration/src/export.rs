@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export migrations as a SQL script
+
+use std::fmt::Write;
+
+use crate::{
+    sql::{quote_identifier, quote_string, split_statements},
+    Error, Migrations, Result, SchemaVersionError, M,
+};
+
+impl Migrations<'_> {
+    /// Render the migrations from version `from` to version `to` as a SQL script, to review
+    /// or run the SQL by hand. Migrations are reverted when `to` is lower than `from`.
+    ///
+    /// The script runs the statements [`Migrations::to_version`] would run, in a single
+    /// transaction: the SQL of the migrations, the foreign key checks enabled with
+    /// [`M::foreign_key_check`] and the update of the `user_version`. The tables of this library
+    /// are updated too, when [`Migrations::module`] or [`Migrations::store_down_migrations`] are
+    /// used for instance. Hooks can’t be exported, a comment marks where they would run.
+    /// [Repeatable migrations](Migrations::repeatable) are not exported, as
+    /// [`Migrations::to_version`] does not apply them either.
+    ///
+    /// Foreign key checks insert the number of violations in a temporary table whose `CHECK`
+    /// constraint only accepts 0, so that the script fails when a foreign key is violated. Run the
+    /// script with `.bail on` in the `sqlite3` shell, to stop at the first error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    ///     M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+    /// ]);
+    ///
+    /// let script = migrations.export_script(0, 2).unwrap();
+    /// assert!(script.contains("PRAGMA user_version = 2;"));
+    ///
+    /// let conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute_batch(&script).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SpecifiedSchemaVersion`] if `from` or `to` are above the last version
+    /// and [`Error::MigrationDefinition`] if a migration to revert has no down migration.
+    pub fn export_script(&self, from: usize, to: usize) -> Result<String> {
+        if self.is_graph() {
+            return self.ordered(Vec::new())?.export_script(from, to);
+        }
+        for version in [from, to] {
+            if version > self.ms.len() {
+                return Err(Error::SpecifiedSchemaVersion(
+                    SchemaVersionError::TargetVersionOutOfRange {
+                        specified: self.db_version_to_schema(version),
+                        highest: self.max_schema_version(),
+                    },
+                ));
+            }
+        }
+        if to < from {
+            self.check_down_defined(from, to)?;
+        }
+
+        let mut script = format!(
+            "-- Migrations of the {} database from version {from} to version {to}\n\
+            -- Run with `.bail on` in the sqlite3 shell, to stop at the first error\n\
+            BEGIN;\n",
+            self.schema_name()
+        );
+        if from <= to {
+            for v in from..to {
+                self.export_up(&mut script, v);
+            }
+        } else {
+            for v in (to..from).rev() {
+                self.export_down(&mut script, v);
+            }
+        }
+
+        let pragma = match &self.schema {
+            Some(schema) => format!("{}.user_version", quote_identifier(schema)),
+            None => String::from("user_version"),
+        };
+        let _ = write!(script, "\n-- Library state\nPRAGMA {pragma} = {to};\n");
+        if self.declares_compatibility() {
+            script.push_str(&self.compatibility_sql(to));
+            script.push('\n');
+        }
+        if self.records_migrations() {
+            script.push_str(&self.modules_sql(from, to));
+        }
+        if self.store_down {
+            script.push_str(&self.down_migrations_sql(from, to));
+        }
+        script.push_str("COMMIT;\n");
+        Ok(script)
+    }
+
+    fn export_up(&self, script: &mut String, v: usize) {
+        let m = &self.ms[v];
+        header(script, m, v, "applied");
+        push_sql(script, &m.up);
+        self.push_fk_check(script, m);
+        if m.up_hook.is_some() {
+            script.push_str(
+                "-- The up hook of this migration can’t be exported, it would run here\n",
+            );
+        }
+    }
+
+    fn export_down(&self, script: &mut String, v: usize) {
+        let m = &self.ms[v];
+        header(script, m, v, "reverted");
+        if m.down_hook.is_some() {
+            script.push_str(
+                "-- The down hook of this migration can’t be exported, it would run here\n",
+            );
+        }
+        push_sql(script, m.down.as_deref().unwrap_or_default());
+        self.push_fk_check(script, m);
+    }
+
+    fn push_fk_check(&self, script: &mut String, m: &M<'_>) {
+        if m.foreign_key_check {
+            let violations = match &self.schema {
+                Some(schema) => format!("pragma_foreign_key_check(NULL, {})", quote_string(schema)),
+                None => String::from("pragma_foreign_key_check"),
+            };
+            let _ = writeln!(
+                script,
+                "-- Foreign key check: fails if a foreign key is violated
+CREATE TEMP TABLE rusqlite_migration_fk_check (violations INTEGER CHECK (violations = 0));
+INSERT INTO temp.rusqlite_migration_fk_check SELECT count(*) FROM {violations};
+DROP TABLE temp.rusqlite_migration_fk_check;"
+            );
+        }
+    }
+}
+
+fn header(script: &mut String, m: &M<'_>, v: usize, action: &str) {
+    let _ = write!(script, "\n-- Migration {v}");
+    if let Some(comment) = m.comment.as_deref() {
+        let _ = write!(script, " ({})", comment.replace('\n', " "));
+    }
+    let _ = writeln!(script, ", {action}");
+}
+
+/// Add the SQL of a migration, terminating its last statement if needed
+fn push_sql(script: &mut String, sql: &str) {
+    script.push_str(sql.trim());
+    script.push('\n');
+    if split_statements(sql)
+        .last()
+        .is_some_and(|statement| !statement.text.ends_with(';'))
+    {
+        script.push_str(";\n");
+    }
+}
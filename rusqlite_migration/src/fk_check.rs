@@ -17,32 +17,47 @@
 
 use std::cell::OnceCell;
 
-use rusqlite::{Statement, Transaction};
+use rusqlite::{Statement, ToSql, Transaction};
 
 use crate::{Error, ForeignKeyCheckError, Result};
 
 const PRAGMA_FK_CHECK: &str = "SELECT * FROM pragma_foreign_key_check;";
+const PRAGMA_FK_CHECK_SCHEMA: &str = "SELECT * FROM pragma_foreign_key_check(NULL, ?1);";
 
 pub(crate) struct FKCheck<'conn> {
     // Store a result here so Self is easier to lazily initialize
     stmt: OnceCell<Statement<'conn>>,
+    schema: Option<String>,
 }
 
 impl<'conn> FKCheck<'conn> {
     pub(crate) fn new() -> Self {
         Self {
             stmt: OnceCell::new(),
+            schema: None,
+        }
+    }
+
+    /// Check the foreign keys of the tables in `schema`, or in the `main` schema if `None`
+    pub(crate) fn for_schema(schema: Option<&str>) -> Self {
+        Self {
+            stmt: OnceCell::new(),
+            schema: schema.map(str::to_owned),
         }
     }
 
     /// Validate that no foreign keys are violated
     pub(crate) fn validate(&mut self, conn: &'conn Transaction) -> Result<()> {
         // Not great, but get_mut_or_init is still in nightly
+        let query = match self.schema {
+            Some(_) => PRAGMA_FK_CHECK_SCHEMA,
+            None => PRAGMA_FK_CHECK,
+        };
         if self.stmt.get().is_none() {
             self.stmt
                 .set(
-                    conn.prepare(PRAGMA_FK_CHECK)
-                        .map_err(|e| crate::Error::with_sql(e, PRAGMA_FK_CHECK))?,
+                    conn.prepare(query)
+                        .map_err(|e| crate::Error::with_sql(e, query))?,
                 )
                 .expect("OnceCell was checked above and is empty");
         }
@@ -51,8 +66,12 @@ impl<'conn> FKCheck<'conn> {
             .get_mut()
             .expect("the OnceCell was initialize just above");
 
+        let params: &[&dyn ToSql] = match &self.schema {
+            Some(schema) => &[schema],
+            None => &[],
+        };
         let fk_errors = stmt
-            .query_map([], |row| {
+            .query_map(params, |row| {
                 Ok(ForeignKeyCheckError {
                     table: row.get(0)?,
                     rowid: row.get(1)?,
@@ -60,7 +79,7 @@ impl<'conn> FKCheck<'conn> {
                     fkid: row.get(3)?,
                 })
             })
-            .map_err(|e| Error::with_sql(e, PRAGMA_FK_CHECK))?
+            .map_err(|e| Error::with_sql(e, query))?
             .collect::<Result<Vec<_>, _>>()?;
 
         if !fk_errors.is_empty() {
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migrations declaring dependencies on each other, instead of a linear order
+
+use std::{collections::HashMap, fmt, ops::Deref};
+
+use log::{debug, warn};
+use rusqlite::Connection;
+
+use crate::{
+    modules::MODULES_TABLE, user_version_in, Error, MigrationDefinitionError, Migrations, Result, M,
+};
+
+/// Ids of the migrations a migration depends on. Unlike `Cow<'u, [&'u str]>`, this keeps
+/// [`M`] covariant in `'u`. Like for the text of [`M`], [`M::depends_on`] can’t drop the ids it
+/// replaces, so declared and collected ids are kept apart: the last ones set are used.
+#[derive(Clone)]
+pub(crate) struct Dependencies<'u> {
+    /// Given to [`M::depends_on`]
+    pub(crate) declared: Option<&'u [&'u str]>,
+    /// Collected at runtime, from a [`crate::MigrationManifest`] for instance
+    pub(crate) collected: Vec<&'u str>,
+}
+
+impl<'u> Dependencies<'u> {
+    pub(crate) const NONE: Self = Self {
+        declared: None,
+        collected: Vec::new(),
+    };
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn collected(ids: Vec<&'u str>) -> Self {
+        Self {
+            declared: None,
+            collected: ids,
+        }
+    }
+}
+
+impl<'u> Deref for Dependencies<'u> {
+    type Target = [&'u str];
+
+    fn deref(&self) -> &Self::Target {
+        match self.declared {
+            Some(ids) => ids,
+            None => &self.collected,
+        }
+    }
+}
+
+impl PartialEq for Dependencies<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Dependencies<'_> {}
+
+impl fmt::Debug for Dependencies<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'u> M<'u> {
+    /// Declare the ids of the migrations this one depends on, see [`M::id`]. Dependencies must
+    /// be in the same set of migrations.
+    ///
+    /// When some migrations declare dependencies, the set is a graph and its order doesn’t
+    /// matter anymore. All migrations need an id. They are applied in a deterministic
+    /// topological order: each migration comes after its dependencies and, among the migrations
+    /// whose dependencies are all applied, the first in the set is applied first. The ids of
+    /// applied migrations are recorded, so that migrations added later, when merging branches
+    /// developed in parallel for instance, are applied after the ones already applied, without
+    /// renumbering anything.
+    ///
+    /// Versions, as given to [`Migrations::to_version`], count the migrations in the order they
+    /// were applied to the database. A set where no migration declares dependencies stays
+    /// linear.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// let base = M::up("CREATE TABLE animals (name TEXT);").id("animals");
+    /// let legs = M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;")
+    ///     .id("legs")
+    ///     .depends_on(&["animals"]);
+    /// Migrations::new(vec![base.clone(), legs.clone()])
+    ///     .to_latest(&mut conn)
+    ///     .unwrap();
+    ///
+    /// // Merged from another branch, where it was written after the animals table
+    /// let food = M::up("CREATE TABLE food (animal TEXT REFERENCES animals(name));")
+    ///     .id("food")
+    ///     .depends_on(&["animals"]);
+    /// Migrations::new(vec![base, food, legs])
+    ///     .to_latest(&mut conn)
+    ///     .unwrap();
+    /// ```
+    pub const fn depends_on(mut self, ids: &'u [&'u str]) -> Self {
+        self.dependencies.declared = Some(ids);
+        self
+    }
+}
+
+impl<'m> Migrations<'m> {
+    /// Whether some migrations declare dependencies
+    pub(crate) fn is_graph(&self) -> bool {
+        self.ms.iter().any(|m| !m.dependencies.is_empty())
+    }
+
+    /// Linear set of the migrations of this graph, in the order they are applied to `conn`:
+    /// first the migrations already applied, then the others
+    pub(crate) fn linearized(&self, conn: &Connection) -> Result<Migrations<'m>> {
+        let current_version = user_version_in(conn, self.schema.as_deref())?;
+        let recorded = self.recorded_ids(conn, current_version)?;
+
+        let mut applied = Vec::new();
+        for version in 1..=current_version.min(self.ms.len()) {
+            let index = match recorded.get(&version) {
+                Some(id) => (self.ms.iter())
+                    .position(|m| m.id.as_deref() == Some(id))
+                    .ok_or_else(|| Error::UnknownMigrationId(id.clone()))?,
+                // Applied when the set was still linear
+                None => version - 1,
+            };
+            if let Some(position) = applied.iter().position(|&i| i == index) {
+                // Migrations applied without ids were moved since
+                let first_version = position + 1;
+                return Err(Error::MigrationsReordered {
+                    version: first_version,
+                    applied: match recorded.get(&first_version) {
+                        Some(id) => format!("migration “{id}”"),
+                        None => String::from("a migration without id"),
+                    },
+                    defined: format!(
+                        "migration “{}”, also applied at version {version},",
+                        self.ms[index].id.as_deref().unwrap_or_default()
+                    ),
+                });
+            }
+            applied.push(index);
+        }
+        self.ordered(applied)
+    }
+
+    /// Linear set of the migrations of this graph, once the migrations `applied` are, in that
+    /// order
+    pub(crate) fn ordered(&self, applied: Vec<usize>) -> Result<Migrations<'m>> {
+        self.check_ids()?;
+        let order = self.topological_order(applied)?;
+        debug!("migrations ordered as {order:?}");
+
+        let mut linear = self.clone();
+        linear.ms = (order.into_iter())
+            .map(|index| M {
+                dependencies: Dependencies::NONE,
+                ..self.ms[index].clone()
+            })
+            .collect();
+        // Modules are ranges of migrations, which don’t hold once the migrations are reordered
+        linear.modules.clear();
+        Ok(linear)
+    }
+
+    /// Indices of the migrations, starting with `applied` and then with the first migration
+    /// whose dependencies are all ordered, repeatedly
+    fn topological_order(&self, mut applied: Vec<usize>) -> Result<Vec<usize>> {
+        let definition_error = |e| Err(Error::MigrationDefinition(e));
+        let mut indices = HashMap::new();
+        for (index, m) in self.ms.iter().enumerate() {
+            match m.id.as_deref() {
+                Some(id) => indices.insert(id, index),
+                None => {
+                    return definition_error(MigrationDefinitionError::MissingId {
+                        migration_index: index,
+                    })
+                }
+            };
+        }
+        let mut dependencies = Vec::with_capacity(self.ms.len());
+        for (index, m) in self.ms.iter().enumerate() {
+            let Some(deps) = (m.dependencies.iter())
+                .map(|id| indices.get(id).copied())
+                .collect::<Option<Vec<usize>>>()
+            else {
+                warn!("migration {index} depends on an unknown id: {m:?}");
+                return definition_error(MigrationDefinitionError::UnknownDependency {
+                    migration_index: index,
+                });
+            };
+            dependencies.push(deps);
+        }
+
+        let mut ordered = vec![false; self.ms.len()];
+        for &index in &applied {
+            ordered[index] = true;
+        }
+        while applied.len() < self.ms.len() {
+            let Some(next) = (0..self.ms.len())
+                .find(|&i| !ordered[i] && dependencies[i].iter().all(|&d| ordered[d]))
+            else {
+                let index = ordered.iter().position(|&o| !o).unwrap_or_default();
+                warn!("dependencies of migration {index} form a cycle");
+                return definition_error(MigrationDefinitionError::DependencyCycle {
+                    migration_index: index,
+                });
+            };
+            ordered[next] = true;
+            applied.push(next);
+        }
+        Ok(applied)
+    }
+
+    /// Ids of the migrations applied up to `current_version`, by version, when recorded
+    fn recorded_ids(
+        &self,
+        conn: &Connection,
+        current_version: usize,
+    ) -> Result<HashMap<usize, String>> {
+        if !self.table_exists(conn, MODULES_TABLE)? {
+            return Ok(HashMap::new());
+        }
+        let select = format!(
+            "SELECT version, id FROM {} WHERE version <= ?1 AND id IS NOT NULL;",
+            self.qualified(MODULES_TABLE)
+        );
+        let mut stmt = conn
+            .prepare(&select)
+            .map_err(|e| Error::with_sql(e, &select))?;
+        stmt.query_map([current_version as i64], |row| {
+            Ok((row.get::<_, i64>(0)? as usize, row.get(1)?))
+        })
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::with_sql(e, &select))
+    }
+}
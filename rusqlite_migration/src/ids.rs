@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable identifiers of migrations
+
+use std::collections::HashMap;
+
+use log::warn;
+use rusqlite::Connection;
+
+use crate::{
+    user_version_in, Error, MigrationDefinitionError, Migrations, Result, SchemaVersion, Text, M,
+};
+
+impl<'u> M<'u> {
+    /// Give the migration a stable identifier, like a name, a timestamp or a ULID. Ids must be
+    /// unique within a set of migrations.
+    ///
+    /// Migrations are otherwise only identified by their position in the set. When some have an
+    /// id, the id of each migration applied is recorded in the database, so that deleting or
+    /// reordering migrations is detected instead of silently shifting versions, see
+    /// [`Migrations::verify_applied`]. The id can also be used as a target, see
+    /// [`Migrations::to_id`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::M;
+    ///
+    /// M::up("CREATE TABLE animals (name TEXT);").id("20240101_create_animals");
+    /// ```
+    pub const fn id(mut self, id: &'u str) -> Self {
+        self.id.borrowed = Some(id);
+        self
+    }
+
+    /// Like [`Self::id()`], with an id owned by the migration
+    pub fn id_owned(mut self, id: String) -> Self {
+        self.id = Text::owned(id);
+        self
+    }
+}
+
+impl Migrations<'_> {
+    /// Version of the database once the migration with the id `id` is applied, or `None` if no
+    /// migration has this id. See [`M::id`].
+    ///
+    /// When migrations declare dependencies, the version depends on the order they were applied
+    /// to each database, see [`M::depends_on`]. Use [`Migrations::to_id`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, SchemaVersion, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").id("create_animals"),
+    ///     M::up("CREATE TABLE food (name TEXT);").id("create_food"),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     migrations.version_of("create_food"),
+    ///     Some(SchemaVersion::Inside(2.try_into().unwrap()))
+    /// );
+    /// assert_eq!(migrations.version_of("create_fruits"), None);
+    /// ```
+    pub fn version_of(&self, id: &str) -> Option<SchemaVersion> {
+        let index = self.ms.iter().position(|m| m.id.as_deref() == Some(id))?;
+        Some(self.db_version_to_schema(index + 1))
+    }
+
+    /// Migrate the database up or down, so that the migration with the id `id` is the last one
+    /// applied. See [`Migrations::to_version`] and [`M::id`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").id("create_animals"),
+    ///     M::up("CREATE TABLE food (name TEXT);").id("create_food"),
+    /// ]);
+    ///
+    /// migrations.to_id(&mut conn, "create_animals").unwrap();
+    /// conn.execute("INSERT INTO animals (name) VALUES (?)", ["dog"]).unwrap();
+    /// conn.execute("INSERT INTO food (name) VALUES (?)", ["carrot"]).unwrap_err();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownMigrationId`] if no migration has this id. Otherwise, fails like
+    /// [`Migrations::to_version`].
+    pub fn to_id(&self, conn: &mut Connection, id: &str) -> Result<()> {
+        if self.is_graph() {
+            return self.linearized(conn)?.to_id(conn, id);
+        }
+        let version =
+            (self.version_of(id)).ok_or_else(|| Error::UnknownMigrationId(String::from(id)))?;
+        self.to_version(conn, version.into())
+    }
+
+    /// Check that the migrations applied to the database are still the ones defined at the same
+    /// versions, without migrating it. Call this at startup, when the database is not migrated
+    /// by this program for instance.
+    ///
+    /// The migrations applied are recorded when they have an id (see [`M::id`]) or belong to a
+    /// module (see [`Migrations::module`]). Migrations applied before that are not checked.
+    /// [`Migrations::to_latest`] and [`Migrations::to_version`] run the same check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Error, Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").id("create_animals"),
+    ///     M::up("CREATE TABLE food (name TEXT);").id("create_food"),
+    /// ])
+    /// .to_latest(&mut conn)
+    /// .unwrap();
+    ///
+    /// // The first migration was deleted
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE food (name TEXT);").id("create_food"),
+    /// ]);
+    /// assert!(matches!(
+    ///     migrations.verify_applied(&conn),
+    ///     Err(Error::MigrationsReordered { version: 1, .. })
+    /// ));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MigrationsReordered`] if a migration applied is not defined at the same
+    /// version anymore, and [`MigrationDefinitionError::DuplicateId`] if several migrations have
+    /// the same id.
+    pub fn verify_applied(&self, conn: &Connection) -> Result<()> {
+        if self.is_graph() {
+            return self.linearized(conn)?.verify_applied(conn);
+        }
+        let current_version = user_version_in(conn, self.schema.as_deref())?;
+        self.check_modules(conn, current_version)
+    }
+
+    /// Check that ids are unique
+    pub(crate) fn check_ids(&self) -> Result<()> {
+        let mut seen = HashMap::new();
+        for (index, m) in self.ms.iter().enumerate() {
+            let Some(id) = m.id.as_deref() else {
+                continue;
+            };
+            if let Some(first) = seen.insert(id, index) {
+                warn!("migrations {first} and {index} have the same id “{id}”");
+                return Err(Error::MigrationDefinition(
+                    MigrationDefinitionError::DuplicateId {
+                        migration_indices: [first, index],
+                    },
+                ));
+            }
+        }
+        Ok(())
+    }
+}
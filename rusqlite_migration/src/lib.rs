@@ -27,26 +27,73 @@ use rusqlite::{Connection, Transaction};
 #[cfg(feature = "from-directory")]
 use include_dir::Dir;
 
+mod attached;
+#[cfg(feature = "backup")]
+mod backup;
+#[cfg(feature = "backup")]
+pub use backup::Backup;
+#[cfg(feature = "backup")]
+mod rehearsal;
+#[cfg(feature = "backup")]
+pub use rehearsal::{Rehearsal, RehearsalStep};
+
 #[cfg(feature = "from-directory")]
 mod loader;
 #[cfg(feature = "from-directory")]
-use loader::from_directory;
+use loader::{from_directory, repeatable_from_directory};
 
 #[cfg(feature = "from-directory")]
 mod builder;
 #[cfg(feature = "from-directory")]
 pub use builder::MigrationsBuilder;
 
+#[cfg(feature = "collect")]
+mod collect;
+#[cfg(feature = "collect")]
+pub use collect::RegisteredMigration;
+#[cfg(feature = "collect")]
+#[doc(hidden)]
+pub mod __collect {
+    pub use inventory;
+}
+
+mod compat;
+mod declarative;
+mod downgrade;
 mod errors;
+mod export;
 mod fk_check;
+mod graph;
+mod ids;
+mod lint;
+#[cfg(feature = "serde")]
+mod manifest;
+mod modules;
+mod rebuild;
+mod repeatable;
+pub mod schema;
+mod sql;
+mod status;
+mod stored_down;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 #[cfg(test)]
 mod tests;
 
+pub use compat::Compatibility;
+pub use declarative::MigrationPlan;
+pub use downgrade::DowngradePolicy;
 pub use errors::{
-    Error, ForeignKeyCheckError, HookError, HookResult, MigrationDefinitionError, Result,
-    SchemaVersionError,
+    Error, ForeignKeyCheckError, HookError, HookResult, MigrationDefinitionError,
+    MigrationDirection, Result, SchemaVersionError,
 };
+pub use lint::{Lint, LintLevel, LintRule};
+#[cfg(feature = "serde")]
+pub use manifest::{HookRegistry, MigrationEntry, MigrationManifest, RepeatableEntry};
+pub use rebuild::rebuild_table;
+pub use schema::{Schema, SchemaDiff};
+pub use status::{MigrationStatus, Status};
 use std::{
     cmp::{self, Ordering},
     fmt::{self, Debug},
@@ -56,6 +103,9 @@ use std::{
 };
 
 use self::fk_check::FKCheck;
+use self::graph::Dependencies;
+use self::modules::Module;
+use self::repeatable::Repeatable;
 
 /// The number of migrations already applied is stored in a [4 bytes field][sqlite_doc], so the number of migrations is limited.
 ///
@@ -95,15 +145,49 @@ impl Clone for Box<dyn MigrationHook> {
 /// A migration can contain up- and down-hooks, which are incomparable closures.
 /// To signify `M` equality we compare if two migrations either don't have hooks defined (they are set to `None`)
 /// or if the closure memory addresses are the same.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 #[must_use]
 pub struct M<'u> {
-    up: &'u str,
+    up: Cow<'u, str>,
     up_hook: Option<Box<dyn MigrationHook>>,
-    down: Option<&'u str>,
+    down: Text<'u>,
     down_hook: Option<Box<dyn MigrationHook>>,
     foreign_key_check: bool,
-    comment: Option<&'u str>,
+    comment: Text<'u>,
+    id: Text<'u>,
+    dependencies: Dependencies<'u>,
+    allowed_lints: &'u [LintRule],
+    non_destructive: bool,
+    read_compatible_since: Option<usize>,
+}
+
+impl Debug for M<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("M");
+        d.field("up", &self.up)
+            .field("up_hook", &self.up_hook)
+            .field("down", &self.down)
+            .field("down_hook", &self.down_hook)
+            .field("foreign_key_check", &self.foreign_key_check)
+            .field("comment", &self.comment);
+        // Only show options that are set, like for Migrations
+        if let Some(id) = self.id.as_deref() {
+            d.field("id", &id);
+        }
+        if !self.dependencies.is_empty() {
+            d.field("dependencies", &self.dependencies);
+        }
+        if !self.allowed_lints.is_empty() {
+            d.field("allowed_lints", &self.allowed_lints);
+        }
+        if self.non_destructive {
+            d.field("non_destructive", &self.non_destructive);
+        }
+        if let Some(since) = &self.read_compatible_since {
+            d.field("read_compatible_since", since);
+        }
+        d.finish()
+    }
 }
 
 impl Display for M<'_> {
@@ -115,25 +199,45 @@ impl Display for M<'_> {
             down_hook,
             foreign_key_check,
             comment,
+            id,
+            dependencies,
+            allowed_lints,
+            non_destructive,
+            read_compatible_since,
         } = self;
         let nl = if f.alternate() { "\n" } else { "" };
         let ind = if f.alternate() { "\n    " } else { "" };
         write!(f, r#"M({ind}up: "{up}""#)?;
+        if let Some(id) = id.as_deref() {
+            write!(f, r#", {ind}id: "{id}""#)?;
+        }
+        if !dependencies.is_empty() {
+            write!(f, ", {ind}depends on: {:?}", &**dependencies)?;
+        }
         if up_hook.is_some() {
             write!(f, ", {ind}up hook")?;
         }
-        if let Some(down) = down {
+        if let Some(down) = down.as_deref() {
             write!(f, r#", {ind}down: "{down}""#)?;
         }
+        if *non_destructive {
+            write!(f, ", {ind}non-destructive")?;
+        }
         if down_hook.is_some() {
             write!(f, ", {ind}down hook")?;
         }
         if *foreign_key_check {
             write!(f, ", {ind}foreign key check")?;
         }
-        if let Some(comment) = comment {
+        if let Some(comment) = comment.as_deref() {
             write!(f, r#", {ind}comment: "{comment}""#)?;
         }
+        if let Some(version) = read_compatible_since {
+            write!(f, ", {ind}read-compatible since: {version}")?;
+        }
+        if !allowed_lints.is_empty() {
+            write!(f, ", {ind}allowed lints: {allowed_lints:?}")?;
+        }
         write!(f, "{nl})")
     }
 }
@@ -155,15 +259,86 @@ impl PartialEq for M<'_> {
         };
 
         self.up == other.up
+            && self.id == other.id
+            && self.dependencies == other.dependencies
             && self.down == other.down
             && equal_up_hooks
             && equal_down_hooks
             && self.foreign_key_check == other.foreign_key_check
+            && self.allowed_lints == other.allowed_lints
+            && self.non_destructive == other.non_destructive
+            && self.read_compatible_since == other.read_compatible_since
     }
 }
 
 impl Eq for M<'_> {}
 
+/// Text of a field of [`M`], like its comment. Const builder methods can’t drop the text they
+/// replace, so borrowed and owned text are kept apart: the last one set is used.
+#[derive(Clone)]
+struct Text<'u> {
+    borrowed: Option<&'u str>,
+    owned: Option<String>,
+}
+
+impl<'u> Text<'u> {
+    const NONE: Self = Self {
+        borrowed: None,
+        owned: None,
+    };
+
+    fn owned(text: String) -> Self {
+        Self {
+            borrowed: None,
+            owned: Some(text),
+        }
+    }
+
+    fn as_deref(&self) -> Option<&str> {
+        self.borrowed.or(self.owned.as_deref())
+    }
+
+    fn is_some(&self) -> bool {
+        self.as_deref().is_some()
+    }
+
+    fn is_none(&self) -> bool {
+        self.as_deref().is_none()
+    }
+}
+
+impl<'u> From<Option<&'u str>> for Text<'u> {
+    fn from(text: Option<&'u str>) -> Self {
+        Self {
+            borrowed: text,
+            owned: None,
+        }
+    }
+}
+
+impl<'u> From<Cow<'u, str>> for Text<'u> {
+    fn from(text: Cow<'u, str>) -> Self {
+        match text {
+            Cow::Borrowed(text) => Self::from(Some(text)),
+            Cow::Owned(text) => Self::owned(text),
+        }
+    }
+}
+
+impl PartialEq for Text<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_deref() == other.as_deref()
+    }
+}
+
+impl Eq for Text<'_> {}
+
+impl Debug for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.as_deref(), f)
+    }
+}
+
 impl<'u> M<'u> {
     /// Create a schema update. The SQL command will be executed only when the migration has not been
     /// executed on the underlying database.
@@ -202,18 +377,54 @@ impl<'u> M<'u> {
     /// [jm]: https://sqlite.org/pragma.html#pragma_journal_mode
     pub const fn up(sql: &'u str) -> Self {
         Self {
-            up: sql,
+            up: Cow::Borrowed(sql),
             up_hook: None,
-            down: None,
+            down: Text::NONE,
             down_hook: None,
             foreign_key_check: false,
-            comment: None,
+            comment: Text::NONE,
+            id: Text::NONE,
+            dependencies: Dependencies::NONE,
+            allowed_lints: &[],
+            non_destructive: false,
+            read_compatible_since: None,
         }
     }
 
     /// Add a comment to the schema update
     pub const fn comment(mut self, comment: &'u str) -> Self {
-        self.comment = Some(comment);
+        self.comment.borrowed = Some(comment);
+        self
+    }
+
+    /// Create a schema update from SQL owned by the migration, built at runtime for instance. See
+    /// [`Self::up()`] for additional notes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let tables = ["animals", "food"];
+    /// let migrations: Migrations<'static> = (tables.iter())
+    ///     .map(|table| {
+    ///         M::up_owned(format!("CREATE TABLE {table} (name TEXT);"))
+    ///             .down_owned(format!("DROP TABLE {table};"))
+    ///             .comment_owned(format!("Create {table}"))
+    ///     })
+    ///     .collect();
+    /// # let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// # migrations.to_latest(&mut conn).unwrap();
+    /// ```
+    pub fn up_owned(sql: String) -> Self {
+        let mut m = Self::up("");
+        m.up = Cow::Owned(sql);
+        m
+    }
+
+    /// Like [`Self::comment()`], with a comment owned by the migration
+    pub fn comment_owned(mut self, comment: String) -> Self {
+        self.comment = Text::owned(comment);
         self
     }
 
@@ -258,8 +469,9 @@ impl<'u> M<'u> {
     ///     ),
     /// ]);
     /// ```
-    pub fn up_with_hook(sql: &'u str, hook: impl MigrationHook + 'static) -> Self {
-        let mut m = Self::up(sql);
+    pub fn up_with_hook(sql: impl Into<Cow<'u, str>>, hook: impl MigrationHook + 'static) -> Self {
+        let mut m = Self::up("");
+        m.up = sql.into();
         m.up_hook = Some(hook.clone_box());
         m
     }
@@ -278,15 +490,26 @@ impl<'u> M<'u> {
     ///     .down("DROP TABLE animals;");
     /// ```
     pub const fn down(mut self, sql: &'u str) -> Self {
-        self.down = Some(sql);
+        self.down.borrowed = Some(sql);
+        self
+    }
+
+    /// Like [`Self::down()`], with SQL owned by the migration. See [`Self::up_owned()`] for an
+    /// example.
+    pub fn down_owned(mut self, sql: String) -> Self {
+        self.down = Text::owned(sql);
         self
     }
 
     /// Define a down-migration running additional Rust code. This SQL statement should exactly
     /// reverse the changes performed in [`Self::up_with_hook()`]. `hook` will run before the SQL
     /// statement is executed.
-    pub fn down_with_hook(mut self, sql: &'u str, hook: impl MigrationHook + 'static) -> Self {
-        self.down = Some(sql);
+    pub fn down_with_hook(
+        mut self,
+        sql: impl Into<Cow<'u, str>>,
+        hook: impl MigrationHook + 'static,
+    ) -> Self {
+        self.down = Text::from(sql.into());
         self.down_hook = Some(hook.clone_box());
         self
     }
@@ -345,8 +568,44 @@ impl<'u> M<'u> {
     }
 }
 
+impl M<'_> {
+    /// Apply the migration in a transaction
+    fn run_up<'conn>(&self, tx: &'conn Transaction, fk_check: &mut FKCheck<'conn>) -> Result<()> {
+        execute_statements(tx, &self.up)?;
+
+        if self.foreign_key_check {
+            fk_check.validate(tx)?
+        }
+
+        if let Some(hook) = &self.up_hook {
+            hook(tx)?;
+        }
+        Ok(())
+    }
+
+    /// Revert the migration in a transaction. The down migration must be defined.
+    fn run_down<'conn>(&self, tx: &'conn Transaction, fk_check: &mut FKCheck<'conn>) -> Result<()> {
+        let Some(down) = self.down.as_deref() else {
+            unreachable!();
+        };
+        debug!("Running: {}", &down);
+
+        if let Some(hook) = &self.down_hook {
+            hook(tx)?;
+        }
+
+        execute_statements(tx, down)?;
+
+        if self.foreign_key_check {
+            fk_check.validate(tx)?
+        }
+        Ok(())
+    }
+}
+
 /// Schema version, in the context of Migrations
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SchemaVersion {
     /// No schema version set
     NoneSet,
@@ -393,9 +652,17 @@ impl cmp::PartialOrd for SchemaVersion {
 }
 
 /// Set of migrations
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone)]
 pub struct Migrations<'m> {
     ms: Cow<'m, [M<'m>]>,
+    lint_levels: Vec<(LintRule, LintLevel)>,
+    downgrade_policy: DowngradePolicy,
+    store_down: bool,
+    schema: Option<String>,
+    modules: Vec<Module>,
+    repeatable: Vec<Repeatable<'m>>,
+    #[cfg(feature = "backup")]
+    backup: Option<Backup>,
 }
 
 impl<'m> Migrations<'m> {
@@ -414,7 +681,17 @@ impl<'m> Migrations<'m> {
     /// ```
     #[must_use]
     pub const fn new(ms: Vec<M<'m>>) -> Self {
-        Self { ms: Cow::Owned(ms) }
+        Self {
+            ms: Cow::Owned(ms),
+            lint_levels: Vec::new(),
+            downgrade_policy: DowngradePolicy::Allow,
+            store_down: false,
+            schema: None,
+            modules: Vec::new(),
+            repeatable: Vec::new(),
+            #[cfg(feature = "backup")]
+            backup: None,
+        }
     }
 
     /// Similar to [`Migrations::new`], but accepts a slice instead. Especially useful in `const`
@@ -435,6 +712,14 @@ impl<'m> Migrations<'m> {
     pub const fn from_slice(ms: &'m [M<'m>]) -> Self {
         Self {
             ms: Cow::Borrowed(ms),
+            lint_levels: Vec::new(),
+            downgrade_policy: DowngradePolicy::Allow,
+            store_down: false,
+            schema: None,
+            modules: Vec::new(),
+            repeatable: Vec::new(),
+            #[cfg(feature = "backup")]
+            backup: None,
         }
     }
 
@@ -458,11 +743,16 @@ impl<'m> Migrations<'m> {
     /// │  └── up.sql
     /// ├── 02-add_birthday_column
     /// │  └── up.sql
-    /// └── 03-add_animal_table
-    ///    ├── down.sql
-    ///    └── up.sql
+    /// ├── 03-add_animal_table
+    /// │  ├── down.sql
+    /// │  └── up.sql
+    /// └── repeatable
+    ///    └── friend_names.sql
     /// ```
     ///
+    /// The `.sql` files of the `repeatable` subdirectory are repeatable migrations, named after
+    /// the files, see [`Migrations::repeatable`].
+    ///
     /// # Example
     ///
     /// ```
@@ -484,7 +774,45 @@ impl<'m> Migrations<'m> {
             .collect::<Option<Cow<_>>>()
             .ok_or(Error::FileLoad("Could not load migrations".to_string()))?;
 
-        Ok(Self { ms: migrations })
+        Ok(Self {
+            ms: migrations,
+            lint_levels: Vec::new(),
+            downgrade_policy: DowngradePolicy::Allow,
+            store_down: false,
+            schema: None,
+            modules: Vec::new(),
+            repeatable: repeatable_from_directory(dir)?,
+            #[cfg(feature = "backup")]
+            backup: None,
+        })
+    }
+
+    /// Back up the database before running migrations with [`Migrations::to_latest`] or
+    /// [`Migrations::to_version`]. No backup is made when the database is already at the target
+    /// version.
+    ///
+    /// When a migration fails, the database is restored from the backup and
+    /// [`Error::Backup`] is returned, giving the location of the backup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Backup, Migrations, M};
+    ///
+    /// let dir = std::env::temp_dir().join("rusqlite_migration_backup_example");
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// let migrations = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")])
+    ///     .backup(Backup::new(&dir).prefix("animals").keep_last(3));
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// migrations.to_latest(&mut conn).unwrap();
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    #[cfg(feature = "backup")]
+    #[must_use]
+    pub fn backup(mut self, backup: Backup) -> Self {
+        self.backup = Some(backup);
+        self
     }
 
     fn db_version_to_schema(&self, db_version: usize) -> SchemaVersion {
@@ -527,7 +855,7 @@ impl<'m> Migrations<'m> {
     /// Returns [`Error::RusqliteError`] or [`Error::InvalidUserVersion`] in case the user
     /// version cannot be queried.
     pub fn current_version(&self, conn: &Connection) -> Result<SchemaVersion> {
-        user_version(conn).map(|v| self.db_version_to_schema(v))
+        user_version_in(conn, self.schema.as_deref()).map(|v| self.db_version_to_schema(v))
     }
 
     /// Returns the number of migrations that would be applied by [`Migrations::to_latest`]. For
@@ -536,7 +864,7 @@ impl<'m> Migrations<'m> {
     /// The number returned may be negative. This happens when more migrations were applied than
     /// the current version of the program knows about. It then represent the number of migrations
     /// applied beyond that point. You can also see it as the number of migrations that would need
-    /// to be undone.
+    /// to be undone. See [`Migrations::status`] for the state of each migration.
     ///
     /// <div class="warning">
     ///
@@ -609,68 +937,103 @@ impl<'m> Migrations<'m> {
     /// Returns [`Error::RusqliteError`] or [`Error::InvalidUserVersion`] in case the user
     /// version cannot be queried.
     pub fn pending_migrations(&self, conn: &Connection) -> Result<i32> {
-        Ok(self.ms.len() as i32 - user_version(conn)? as i32)
+        Ok(self.ms.len() as i32 - user_version_in(conn, self.schema.as_deref())? as i32)
     }
 
     /// Migrate upward methods. This is rolled back on error.
     /// On success, returns the number of update performed
-    /// All versions are db versions
+    /// All versions are db versions. With `repeatable`, the repeatable migrations that changed
+    /// are applied in the same transaction.
     fn goto_up(
         &self,
         conn: &mut Connection,
         current_version: usize,
         target_version: usize,
+        repeatable: bool,
     ) -> Result<()> {
-        debug_assert!(current_version <= target_version);
-        debug_assert!(target_version <= self.ms.len());
-
         trace!("start migration transaction");
-
         let tx = conn.transaction()?;
-        {
-            let mut fk_check = FKCheck::new();
-            for v in current_version..target_version {
-                let m = &self.ms[v];
-                debug!("Running: {}", m.up);
-
-                tx.execute_batch(m.up)
-                    .map_err(|e| Error::with_sql(e, m.up))?;
-
-                if m.foreign_key_check {
-                    fk_check.validate(&tx)?
-                }
-
-                if let Some(hook) = &m.up_hook {
-                    hook(&tx)?;
-                }
-            }
-
-            set_user_version(&tx, target_version)?;
+        self.migrate_up(&tx, current_version, target_version)?;
+        if repeatable {
+            self.run_repeatable(&tx)?;
         }
         tx.commit()?;
         trace!("committed migration transaction");
-
         Ok(())
     }
 
+    /// Apply migrations in an existing transaction
+    fn migrate_up(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        debug_assert!(current_version <= target_version);
+        debug_assert!(target_version <= self.ms.len());
+
+        let mut fk_check = FKCheck::for_schema(self.schema.as_deref());
+        for v in current_version..target_version {
+            let m = &self.ms[v];
+            debug!("Running: {}", m.up);
+
+            m.run_up(tx, &mut fk_check).map_err(|e| {
+                e.in_migration(
+                    v,
+                    MigrationDirection::Up,
+                    target_version,
+                    m.comment.as_deref(),
+                )
+            })?;
+        }
+
+        self.record_version(tx, current_version, target_version)
+    }
+
+    /// Record the version reached in the database, along with the bookkeeping of the library
+    fn record_version(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        set_user_version(tx, self.schema.as_deref(), target_version)?;
+        self.write_compatibility(tx, target_version)?;
+        self.write_modules(tx, current_version, target_version)?;
+        self.write_down_migrations(tx, current_version, target_version)
+    }
+
     /// Migrate downward. This is rolled back on error.
-    /// All versions are db versions
+    /// All versions are db versions. Versions above the known migrations are reverted with the
+    /// stored down migrations. See [`Self::goto_up`] for `repeatable`.
     fn goto_down(
         &self,
         conn: &mut Connection,
         current_version: usize,
         target_version: usize,
+        repeatable: bool,
     ) -> Result<()> {
-        debug_assert!(current_version >= target_version);
-        debug_assert!(target_version <= self.ms.len());
+        self.check_down_defined(current_version, target_version)?;
+        trace!("start migration transaction");
+        let tx = conn.transaction()?;
+        self.migrate_down(&tx, current_version, target_version)?;
+        if repeatable {
+            self.run_repeatable(&tx)?;
+        }
+        tx.commit()?;
+        trace!("committed migration transaction");
+        Ok(())
+    }
 
-        // First, check if all the migrations have a "down" version
+    /// Check if all the migrations to revert have a "down" version
+    fn check_down_defined(&self, current_version: usize, target_version: usize) -> Result<()> {
+        let known_version = current_version.min(self.ms.len());
         if let Some((i, bad_m)) = self
             .ms
             .iter()
             .enumerate()
             .skip(target_version)
-            .take(current_version - target_version)
+            .take(known_version.saturating_sub(target_version))
             .find(|(_, m)| m.down.is_none())
         {
             warn!("Cannot revert: {bad_m:?}");
@@ -678,68 +1041,108 @@ impl<'m> Migrations<'m> {
                 MigrationDefinitionError::DownNotDefined { migration_index: i },
             ));
         }
+        Ok(())
+    }
 
-        trace!("start migration transaction");
-        let tx = conn.transaction()?;
-        {
-            let mut fk_check = FKCheck::new();
-            for v in (target_version..current_version).rev() {
-                let m = &self.ms[v];
-                if let Some(down) = m.down {
-                    debug!("Running: {}", &down);
-
-                    if let Some(hook) = &m.down_hook {
-                        hook(&tx)?;
-                    }
-
-                    tx.execute_batch(down)
-                        .map_err(|e| Error::with_sql(e, down))?;
-
-                    if m.foreign_key_check {
-                        fk_check.validate(&tx)?
-                    }
-                } else {
-                    unreachable!();
-                }
-            }
-            set_user_version(&tx, target_version)?;
+    /// Revert migrations in an existing transaction
+    fn migrate_down(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        debug_assert!(current_version >= target_version);
+        debug_assert!(target_version <= self.ms.len());
+        let known_version = current_version.min(self.ms.len());
+
+        if current_version > known_version {
+            self.revert_stored(tx, current_version, target_version)?;
         }
-        tx.commit()?;
-        trace!("committed migration transaction");
-        Ok(())
+        let mut fk_check = FKCheck::for_schema(self.schema.as_deref());
+        for v in (target_version..known_version).rev() {
+            let m = &self.ms[v];
+            m.run_down(tx, &mut fk_check).map_err(|e| {
+                e.in_migration(
+                    v,
+                    MigrationDirection::Down,
+                    target_version,
+                    m.comment.as_deref(),
+                )
+            })?;
+        }
+        self.record_version(tx, current_version, target_version)
     }
 
-    /// Go to a given db version
-    fn goto(&self, conn: &mut Connection, target_db_version: usize) -> Result<()> {
-        let current_version = user_version(conn)?;
+    /// Go to a given db version. With `repeatable`, the repeatable migrations that changed are
+    /// applied too, in the same transaction.
+    fn goto(
+        &self,
+        conn: &mut Connection,
+        target_db_version: usize,
+        repeatable: bool,
+    ) -> Result<()> {
+        if self.is_graph() {
+            return self
+                .linearized(conn)?
+                .goto(conn, target_db_version, repeatable);
+        }
+        let current_version = user_version_in(conn, self.schema.as_deref())?;
+        self.check_downgrade(current_version, target_db_version)?;
+        self.check_modules(conn, current_version)?;
+
+        #[cfg(feature = "backup")]
+        let backup = match &self.backup {
+            Some(backup) if target_db_version != current_version => Some((
+                backup,
+                backup.create(conn, self.schema_name(), current_version)?,
+            )),
+            _ => None,
+        };
 
         let res = match target_db_version.cmp(&current_version) {
             Ordering::Less => {
-                if current_version > self.ms.len() {
-                    return Err(Error::MigrationDefinition(
+                if current_version > self.ms.len() && !self.store_down {
+                    Err(Error::MigrationDefinition(
                         MigrationDefinitionError::DatabaseTooFarAhead,
-                    ));
+                    ))
+                } else {
+                    debug!(
+                        "rollback to older version requested, target_db_version: {target_db_version}, current_version: {current_version}",
+                    );
+                    self.goto_down(conn, current_version, target_db_version, repeatable)
                 }
-                debug!(
-                    "rollback to older version requested, target_db_version: {target_db_version}, current_version: {current_version}",
-                );
-                self.goto_down(conn, current_version, target_db_version)
             }
             Ordering::Equal => {
                 debug!("no migration to run, db already up to date");
-                return Ok(()); // return directly, so the migration message is not printed
+                // return directly, so the migration message is not printed
+                return if repeatable {
+                    self.apply_repeatable(conn)
+                } else {
+                    Ok(())
+                };
             }
             Ordering::Greater => {
                 debug!(
                     "some migrations to run, target: {target_db_version}, current: {current_version}"
                 );
-                self.goto_up(conn, current_version, target_db_version)
+                self.goto_up(conn, current_version, target_db_version, repeatable)
             }
         };
 
         if res.is_ok() {
             info!("Database migrated to version {target_db_version}");
         }
+
+        #[cfg(feature = "backup")]
+        if let Some((backup, path)) = backup {
+            return match res {
+                Ok(()) => {
+                    backup.succeeded(&path);
+                    Ok(())
+                }
+                Err(e) => Err(backup.failed(conn, self.schema_name(), e, path)),
+            };
+        }
         res
     }
 
@@ -754,6 +1157,8 @@ impl<'m> Migrations<'m> {
     }
 
     /// Migrate the database to latest schema version. The migrations are applied atomically.
+    /// [Repeatable migrations](Migrations::repeatable) that changed are applied next, in the same
+    /// transaction.
     ///
     /// # Example
     ///
@@ -778,25 +1183,42 @@ impl<'m> Migrations<'m> {
     ///
     /// Returns [`Error::MigrationDefinition`] if no migration is defined.
     ///
-    /// Returns [`Error::RusqliteError`] if rusqlite returns an error when executing a migration
-    /// statement. Note that this immediatley stops applying migrations.
+    /// Returns [`Error::Migration`] if a migration fails, for instance when rusqlite returns an
+    /// error when executing a migration statement. The error gives the index and comment of the
+    /// failing migration, along with the underlying error. Statements are executed one by one, so
+    /// SQL errors are [`Error::Statement`], giving the failing statement and its location in the
+    /// migration. Note that this immediatley stops applying migrations.
+    ///
+    /// Returns [`Error::Repeatable`] if a repeatable migration fails.
     /// ```rust
-    /// # use rusqlite_migration::{Migrations, M};
+    /// # use rusqlite_migration::{Error, Migrations, MigrationDirection, M};
     /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
     ///
     /// let migrations = Migrations::new(vec![
     ///     M::up("CREATE TABLE t1 (c);"),
-    ///     M::up("SYNTAX ERROR"),         // This won’t be applied
-    ///     M::up("CREATE TABLE t2 (c);"), // This won’t be applied either because the migration above
+    ///     // This won’t be applied, the error is at line 2, column 1 of the migration
+    ///     M::up("CREATE TABLE t2 (c);\nSYNTAX ERROR;"),
+    ///     M::up("CREATE TABLE t3 (c);"), // This won’t be applied either because the migration above
     ///                                    // failed
     /// ]);
     ///
+    /// let Err(Error::Migration {
+    ///     migration_index: 1,
+    ///     direction: MigrationDirection::Up,
+    ///     err,
+    ///     ..
+    /// }) = migrations.to_latest(&mut conn)
+    /// else {
+    ///     panic!("the second migration should fail");
+    /// };
     /// assert!(matches!(
-    ///     migrations.to_latest(&mut conn),
-    ///     Err(rusqlite_migration::Error::RusqliteError {
-    ///         query: _,
+    ///     *err,
+    ///     Error::Statement {
+    ///         line: 2,
+    ///         column: 1,
     ///         err: rusqlite::Error::SqliteFailure(_, _),
-    ///     })
+    ///         ..
+    ///     }
     /// ));
     /// ```
     /// If rusqlite `extra_check` feature is enabled, any migration that returns a value will error
@@ -825,7 +1247,7 @@ impl<'m> Migrations<'m> {
             }
             SchemaVersion::Inside(v) => {
                 debug!("some migrations defined (version: {v}), try to migrate");
-                self.goto(conn, v_max.into())
+                self.goto(conn, v_max.into(), true)
             }
             SchemaVersion::Outside(_) => unreachable!(),
         }
@@ -872,6 +1294,9 @@ impl<'m> Migrations<'m> {
     ///
     /// When migrating downwards, all the reversed migrations must have a `.down()` variant,
     /// otherwise no migrations are run and the function returns an error.
+    ///
+    /// Like for [`Migrations::to_latest`], a failing migration is reported with
+    /// [`Error::Migration`].
     pub fn to_version(&self, conn: &mut Connection, version: usize) -> Result<()> {
         let target_version: SchemaVersion = self.db_version_to_schema(version);
         let v_max = self.max_schema_version();
@@ -894,7 +1319,7 @@ impl<'m> Migrations<'m> {
                     ));
                 }
 
-                self.goto(conn, target_version.into())
+                self.goto(conn, target_version.into(), false)
             }
             SchemaVersion::Outside(_) => unreachable!(
                 "max_schema_version should not return SchemaVersion::Outside.
@@ -906,6 +1331,9 @@ impl<'m> Migrations<'m> {
     /// Run upward migrations on a temporary in-memory database from first to last, one by one.
     /// Convenience method for testing.
     ///
+    /// Migrations are [linted](Migrations::lint) first: lints are logged as warnings, or make
+    /// the validation fail if they are denied with [`Migrations::lint_level`].
+    ///
     /// # Example
     ///
     /// ```
@@ -923,21 +1351,170 @@ impl<'m> Migrations<'m> {
     ///
     /// # Errors
     ///
+    /// Returns [`Error::Lint`] if denied lints are found.
+    ///
     /// Returns [`Error::RusqliteError`] if the underlying sqlite database open call fails.
     pub fn validate(&self) -> Result<()> {
-        let mut conn = Connection::open_in_memory()?;
+        self.check_lints()?;
+        let mut conn = self.open_scratch()?;
         self.to_latest(&mut conn)
     }
+
+    /// Apply migrations one by one on a temporary in-memory database and return the [`Schema`]
+    /// after each of them, starting with [`SchemaVersion::NoneSet`] (empty database).
+    ///
+    /// The schemas are deterministic and normalized. This makes them suitable for golden file
+    /// tests, so that every schema change shows up in code review as a readable diff.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);"),
+    ///     M::up("CREATE INDEX animals_name ON animals(name);"),
+    /// ]);
+    ///
+    /// for (version, schema) in migrations.schema_snapshots().unwrap() {
+    ///     // With insta, for instance:
+    ///     // insta::assert_snapshot!(format!("version_{}", usize::from(version)), schema);
+    ///     println!("{version}:\n{schema}");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MigrationDefinitionError::NoMigrationsDefined`] if there are no migrations.
+    /// Other errors are returned as they would be by [`Migrations::to_latest`].
+    pub fn schema_snapshots(&self) -> Result<Vec<(SchemaVersion, Schema)>> {
+        if self.is_graph() {
+            return self.ordered(Vec::new())?.schema_snapshots();
+        }
+        if self.ms.is_empty() {
+            return Err(Error::MigrationDefinition(
+                MigrationDefinitionError::NoMigrationsDefined,
+            ));
+        }
+
+        let mut conn = self.open_scratch()?;
+        let mut snapshots = Vec::with_capacity(self.ms.len() + 1);
+        snapshots.push((
+            SchemaVersion::NoneSet,
+            Schema::read_in(&conn, self.schema.as_deref())?,
+        ));
+        for v in 0..self.ms.len() {
+            self.goto_up(&mut conn, v, v + 1, false)?;
+            snapshots.push((
+                self.db_version_to_schema(v + 1),
+                Schema::read_in(&conn, self.schema.as_deref())?,
+            ));
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Stricter version of [`Migrations::validate`], checking that down migrations revert their
+    /// up counterpart. On a temporary in-memory database, for each version `N`, this goes up to
+    /// `N`, down to `N - 1` and back up to `N`. The [`Schema`] (read from the [`sqlite_schema`
+    /// table][schema_table]) is compared at each step and must be the same before the up
+    /// migration and after the down migration.
+    ///
+    /// Migrations without a down step are applied, but the round trip is skipped for them.
+    ///
+    /// Only the schema is compared, changes to the data are not detected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Error, MigrationDefinitionError, Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    ///     // Oops, the index is not dropped
+    ///     M::up("CREATE INDEX animals_name ON animals(name);").down("-- TODO"),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     migrations.validate_reversible(),
+    ///     Err(Error::MigrationDefinition(
+    ///         MigrationDefinitionError::SchemaNotRestored { migration_index: 1 }
+    ///     ))
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MigrationDefinitionError::SchemaNotRestored`] for the first migration whose
+    /// down step does not restore the schema, or [`MigrationDefinitionError::NoMigrationsDefined`]
+    /// if there are no migrations. Other errors are returned as they would be by
+    /// [`Migrations::to_version`].
+    ///
+    /// [schema_table]: https://www.sqlite.org/schematab.html
+    pub fn validate_reversible(&self) -> Result<()> {
+        if self.is_graph() {
+            return self.ordered(Vec::new())?.validate_reversible();
+        }
+        if self.ms.is_empty() {
+            return Err(Error::MigrationDefinition(
+                MigrationDefinitionError::NoMigrationsDefined,
+            ));
+        }
+
+        let mut conn = self.open_scratch()?;
+        for (i, m) in self.ms.iter().enumerate() {
+            let before_up = Schema::read_in(&conn, self.schema.as_deref())?;
+            self.goto_up(&mut conn, i, i + 1, false)?;
+            if m.down.is_none() {
+                debug!("no down migration for migration {i}, skipping the round trip");
+                continue;
+            }
+            let after_up = Schema::read_in(&conn, self.schema.as_deref())?;
+
+            let not_restored = |expected: &Schema, actual: &Schema| {
+                warn!(
+                    "migration {i} is not reversible: {m:?}\nschema differences:\n{}",
+                    expected.diff(actual)
+                );
+                Err(Error::MigrationDefinition(
+                    MigrationDefinitionError::SchemaNotRestored { migration_index: i },
+                ))
+            };
+
+            self.goto_down(&mut conn, i + 1, i, false)?;
+            let after_down = Schema::read_in(&conn, self.schema.as_deref())?;
+            if after_down != before_up {
+                return not_restored(&before_up, &after_down);
+            }
+            self.goto_up(&mut conn, i, i + 1, false)?;
+            let after_second_up = Schema::read_in(&conn, self.schema.as_deref())?;
+            if after_second_up != after_up {
+                return not_restored(&after_up, &after_second_up);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Execute the statements of a migration one by one, to report which one fails
+fn execute_statements(tx: &Transaction, sql: &str) -> Result<()> {
+    for statement in sql::split_statements(sql) {
+        tx.execute_batch(statement.text)
+            .map_err(|e| Error::in_statement(e, &statement, sql))?;
+    }
+    Ok(())
 }
 
-// Read user version field from the SQLite db
-fn user_version(conn: &Connection) -> Result<usize> {
+// Version of the database `schema`, or of the `main` database if `None`
+fn user_version_in(conn: &Connection, schema: Option<&str>) -> Result<usize> {
+    let query = match schema {
+        Some(schema) => format!("PRAGMA {}.user_version;", sql::quote_identifier(schema)),
+        None => String::from("PRAGMA user_version;"),
+    };
     // We can’t fix this without breaking API compatibility
-    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
-        .map_err(|e| Error::RusqliteError {
-            query: String::from("PRAGMA user_version;"),
-            err: e,
-        })
+    conn.query_row(&query, [], |row| row.get(0))
+        .map_err(|e| Error::RusqliteError { query, err: e })
         .and_then(|v: i32| {
             if v >= 0 {
                 Ok(v as usize)
@@ -947,8 +1524,8 @@ fn user_version(conn: &Connection) -> Result<usize> {
         })
 }
 
-// Set user version field from the SQLite db
-fn set_user_version(conn: &Connection, v: usize) -> Result<()> {
+// Set the version of the database `schema`, or of the `main` database if `None`
+fn set_user_version(conn: &Connection, schema: Option<&str>, v: usize) -> Result<()> {
     trace!("set user version to: {v}");
     let v = if v > MIGRATIONS_MAX {
         Err(Error::SpecifiedSchemaVersion(SchemaVersionError::TooHigh))
@@ -960,17 +1537,56 @@ fn set_user_version(conn: &Connection, v: usize) -> Result<()> {
             )
         }))
     }?;
-    conn.pragma_update(None, "user_version", v)
+    conn.pragma_update(schema, "user_version", v)
         .map_err(|e| Error::RusqliteError {
             query: format!("PRAGMA user_version = {v}; -- Approximate query"),
             err: e,
         })
 }
 
+impl Debug for Migrations<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Migrations");
+        d.field("ms", &self.ms);
+        // Only show options that are set, so that the output does not depend on the features
+        if !self.lint_levels.is_empty() {
+            d.field("lint_levels", &self.lint_levels);
+        }
+        if self.downgrade_policy != DowngradePolicy::Allow {
+            d.field("downgrade_policy", &self.downgrade_policy);
+        }
+        if self.store_down {
+            d.field("store_down", &self.store_down);
+        }
+        if let Some(schema) = &self.schema {
+            d.field("schema", schema);
+        }
+        if !self.modules.is_empty() {
+            d.field("modules", &self.modules);
+        }
+        if !self.repeatable.is_empty() {
+            d.field("repeatable", &self.repeatable);
+        }
+        #[cfg(feature = "backup")]
+        if let Some(backup) = &self.backup {
+            d.field("backup", backup);
+        }
+        d.finish()
+    }
+}
+
 impl<'u> FromIterator<M<'u>> for Migrations<'u> {
     fn from_iter<T: IntoIterator<Item = M<'u>>>(iter: T) -> Self {
         Self {
             ms: Cow::Owned(Vec::from_iter(iter)),
+            lint_levels: Vec::new(),
+            downgrade_policy: DowngradePolicy::Allow,
+            store_down: false,
+            schema: None,
+            modules: Vec::new(),
+            repeatable: Vec::new(),
+            #[cfg(feature = "backup")]
+            backup: None,
         }
     }
 }
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lint the SQL of migrations for statements that don’t belong in a transaction
+
+use std::fmt;
+
+use log::warn;
+
+use crate::{
+    sql::{line_column, split_statements, tokenize, Token},
+    Error, MigrationDirection, Migrations, Result, M,
+};
+
+/// Problem looked for by [`Migrations::lint`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum LintRule {
+    /// `BEGIN`, `COMMIT`, `END` or `ROLLBACK`: each migration already runs in a transaction
+    TransactionControl,
+    /// `PRAGMA foreign_keys`, a no-op inside a transaction, see [`M::foreign_key_check`]
+    ForeignKeysPragma,
+    /// `PRAGMA journal_mode`, which can’t be changed inside a transaction
+    JournalModePragma,
+    /// `VACUUM`, which fails inside a transaction
+    Vacuum,
+    /// `ATTACH` or `DETACH`, which fail inside a transaction
+    Attach,
+    /// `DROP TABLE` in a migration with no down migration, so the data can’t be recovered by
+    /// reverting it
+    DropTableWithoutDown,
+}
+
+/// What to do with the findings of a [`LintRule`], see [`Migrations::lint_level`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintLevel {
+    /// Ignore the findings
+    Allow,
+    /// Log the findings in [`Migrations::validate`]
+    #[default]
+    Warn,
+    /// Make [`Migrations::validate`] fail with [`Error::Lint`]
+    Deny,
+}
+
+/// Statement of a migration flagged by a [`LintRule`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Lint {
+    /// Index of the migration
+    pub migration_index: usize,
+    /// Whether the statement is in the up or down migration
+    pub direction: MigrationDirection,
+    /// Rule flagging the statement
+    pub rule: LintRule,
+    /// Level of the rule
+    pub level: LintLevel,
+    /// Statement flagged
+    pub statement: String,
+    /// Line of the statement in the migration text, starting at 1
+    pub line: usize,
+    /// Column of the statement in the migration text, in characters and starting at 1
+    pub column: usize,
+}
+
+impl<'u> M<'u> {
+    /// Don’t lint this migration with the given rules, see [`Migrations::lint`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{LintRule, M};
+    ///
+    /// M::up("DROP TABLE old_animals;").allow_lints(&[LintRule::DropTableWithoutDown]);
+    /// ```
+    pub const fn allow_lints(mut self, rules: &'u [LintRule]) -> Self {
+        self.allowed_lints = rules;
+        self
+    }
+}
+
+impl Migrations<'_> {
+    /// Set the level of a lint rule, [`LintLevel::Warn`] by default for all rules.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Error, LintLevel, LintRule, Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT); VACUUM;")])
+    ///     .lint_level(LintRule::Vacuum, LintLevel::Deny);
+    ///
+    /// assert!(matches!(migrations.validate(), Err(Error::Lint(_))));
+    /// ```
+    #[must_use]
+    pub fn lint_level(mut self, rule: LintRule, level: LintLevel) -> Self {
+        self.lint_levels.retain(|(r, _)| *r != rule);
+        self.lint_levels.push((rule, level));
+        self
+    }
+
+    /// Look for statements that don’t belong in migrations, in the up and down SQL of each
+    /// migration: statements controlling transactions or failing inside them, and tables
+    /// dropped irreversibly. Rules allowed with [`Migrations::lint_level`] or
+    /// [`M::allow_lints`] are skipped. Hooks are not linted.
+    ///
+    /// [`Migrations::validate`] runs this too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{LintRule, Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);"),
+    ///     M::up("PRAGMA foreign_keys = ON;"),
+    /// ]);
+    ///
+    /// let lints = migrations.lint();
+    /// assert_eq!(lints.len(), 1);
+    /// assert_eq!(lints[0].migration_index, 1);
+    /// assert_eq!(lints[0].rule, LintRule::ForeignKeysPragma);
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for (migration_index, m) in self.ms.iter().enumerate() {
+            let texts = [
+                (MigrationDirection::Up, Some(&*m.up)),
+                (MigrationDirection::Down, m.down.as_deref()),
+            ];
+            for (direction, sql) in texts {
+                let Some(sql) = sql else { continue };
+                for statement in split_statements(sql) {
+                    let tokens: Vec<Token> = (tokenize(statement.text).into_iter())
+                        .filter(Token::is_significant)
+                        .collect();
+                    let Some(rule) = statement_rule(&tokens, direction, m.down.is_some()) else {
+                        continue;
+                    };
+                    let level = self.level(rule);
+                    if level == LintLevel::Allow || m.allowed_lints.contains(&rule) {
+                        continue;
+                    }
+                    let (line, column) = line_column(sql, statement.start);
+                    lints.push(Lint {
+                        migration_index,
+                        direction,
+                        rule,
+                        level,
+                        statement: String::from(statement.text),
+                        line,
+                        column,
+                    });
+                }
+            }
+        }
+        lints
+    }
+
+    /// Log lints with the [`LintLevel::Warn`] level and fail on the ones with the
+    /// [`LintLevel::Deny`] level
+    pub(crate) fn check_lints(&self) -> Result<()> {
+        let (denied, warned): (Vec<Lint>, Vec<Lint>) =
+            (self.lint().into_iter()).partition(|lint| lint.level == LintLevel::Deny);
+        for lint in &warned {
+            warn!("{lint}");
+        }
+        if denied.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Lint(denied))
+        }
+    }
+
+    fn level(&self, rule: LintRule) -> LintLevel {
+        (self.lint_levels.iter())
+            .find(|(r, _)| *r == rule)
+            .map_or_else(LintLevel::default, |(_, level)| *level)
+    }
+}
+
+/// Rule flagging the statement made of `tokens`, if any
+fn statement_rule(
+    tokens: &[Token],
+    direction: MigrationDirection,
+    has_down: bool,
+) -> Option<LintRule> {
+    let first = tokens.first()?;
+
+    if ["BEGIN", "COMMIT", "END"].iter().any(|k| first.is_keyword(k))
+        // Rolling back to a savepoint is fine
+        || (first.is_keyword("ROLLBACK") && !tokens.iter().any(|t| t.is_keyword("TO")))
+    {
+        Some(LintRule::TransactionControl)
+    } else if first.is_keyword("PRAGMA") {
+        // The pragma may be prefixed by a schema name
+        let name = if tokens.get(2).is_some_and(|t| t.is_punct('.')) {
+            tokens.get(3)
+        } else {
+            tokens.get(1)
+        }?;
+        if name.is_keyword("foreign_keys") {
+            Some(LintRule::ForeignKeysPragma)
+        } else if name.is_keyword("journal_mode") {
+            Some(LintRule::JournalModePragma)
+        } else {
+            None
+        }
+    } else if first.is_keyword("VACUUM") {
+        Some(LintRule::Vacuum)
+    } else if first.is_keyword("ATTACH") || first.is_keyword("DETACH") {
+        Some(LintRule::Attach)
+    } else if first.is_keyword("DROP")
+        && tokens.get(1).is_some_and(|t| t.is_keyword("TABLE"))
+        && direction == MigrationDirection::Up
+        && !has_down
+    {
+        Some(LintRule::DropTableWithoutDown)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintRule::TransactionControl => write!(
+                f,
+                "transaction control statement, migrations already run in a transaction"
+            ),
+            LintRule::ForeignKeysPragma => {
+                write!(f, "PRAGMA foreign_keys is a no-op inside a transaction")
+            }
+            LintRule::JournalModePragma => write!(
+                f,
+                "PRAGMA journal_mode can’t be changed inside a transaction"
+            ),
+            LintRule::Vacuum => write!(f, "VACUUM fails inside a transaction"),
+            LintRule::Attach => write!(f, "ATTACH and DETACH fail inside a transaction"),
+            LintRule::DropTableWithoutDown => {
+                write!(f, "table dropped in a migration that can’t be reverted")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "migration {} ({}), line {}, column {}: {}: '{}'",
+            self.migration_index, self.direction, self.line, self.column, self.rule, self.statement
+        )
+    }
+}
+
+impl std::error::Error for Lint {}
@@ -15,9 +15,12 @@
 
 use std::{convert::TryFrom, num::NonZeroUsize};
 
-use crate::{Error, Result, M};
+use crate::{repeatable::Repeatable, Error, Result, M};
 use include_dir::Dir;
 
+/// Subdirectory holding repeatable migrations, see [`crate::Migrations::repeatable`]
+const REPEATABLE_DIR: &str = "repeatable";
+
 #[derive(Debug, Clone)]
 struct MigrationFile {
     id: NonZeroUsize,
@@ -108,9 +111,10 @@ impl From<&MigrationFile> for M<'_> {
 
 #[cfg_attr(test, mutants::skip)] // Tested at a high level
 pub(crate) fn from_directory(dir: &'static Dir<'static>) -> Result<Vec<Option<M<'static>>>> {
-    let mut migrations: Vec<Option<M>> = vec![None; dir.dirs().count()];
+    let dirs = || (dir.dirs()).filter(|d| !d.path().ends_with(REPEATABLE_DIR));
+    let mut migrations: Vec<Option<M>> = vec![None; dirs().count()];
 
-    for dir in dir.dirs() {
+    for dir in dirs() {
         let migration_file = MigrationFile::try_from(dir)?;
 
         let id = usize::from(migration_file.id) - 1;
@@ -146,3 +150,31 @@ pub(crate) fn from_directory(dir: &'static Dir<'static>) -> Result<Vec<Option<M<
     // The values are returned in the order of the keys, i.e. of IDs
     Ok(migrations)
 }
+
+/// Load the `.sql` files of the `repeatable` subdirectory, named after the files
+#[cfg_attr(test, mutants::skip)] // Tested at a high level
+pub(crate) fn repeatable_from_directory(
+    dir: &'static Dir<'static>,
+) -> Result<Vec<Repeatable<'static>>> {
+    let Some(repeatable_dir) = dir.dirs().find(|d| d.path().ends_with(REPEATABLE_DIR)) else {
+        return Ok(Vec::new());
+    };
+    (repeatable_dir.files())
+        .filter(|f| f.path().extension().is_some_and(|ext| ext == "sql"))
+        .map(|f| {
+            let name = (f.path().file_stem())
+                .and_then(|name| name.to_str())
+                .ok_or(Error::FileLoad(format!(
+                    "Could not extract file name from {:?}",
+                    f.path()
+                )))?;
+            let sql = f.contents_utf8().ok_or(Error::FileLoad(format!(
+                "Could not load contents from {REPEATABLE_DIR}/{name}.sql"
+            )))?;
+            Ok(Repeatable {
+                name: name.into(),
+                sql: sql.into(),
+            })
+        })
+        .collect()
+}
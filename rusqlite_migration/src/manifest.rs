@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialize migrations and load them from manifests
+
+use std::{collections::HashMap, fmt};
+
+use serde::{
+    ser::{self, SerializeStruct},
+    Deserialize, Serialize, Serializer,
+};
+
+use crate::{graph::Dependencies, Error, LintRule, MigrationHook, Migrations, Result, M};
+
+/// List of migrations, as written in a JSON or TOML file for instance. This is the format
+/// [`Migrations`] is serialized to, so that serialized migrations can be loaded back.
+///
+/// # Example
+///
+/// ```
+/// use rusqlite_migration::{HookRegistry, MigrationManifest, Migrations};
+///
+/// let manifest: MigrationManifest = serde_json::from_str(r#"{
+///     "migrations": [
+///         {"up": "CREATE TABLE animals (name TEXT);", "down": "DROP TABLE animals;"},
+///         {"up": "CREATE TABLE food (name TEXT);", "comment": "Add food", "foreign_key_check": true}
+///     ]
+/// }"#).unwrap();
+/// let migrations = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap();
+///
+/// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+/// migrations.to_latest(&mut conn).unwrap();
+/// ```
+///
+/// With TOML, the same manifest reads:
+///
+/// ```toml
+/// [[migrations]]
+/// up = "CREATE TABLE animals (name TEXT);"
+/// down = "DROP TABLE animals;"
+///
+/// [[migrations]]
+/// up = "CREATE TABLE food (name TEXT);"
+/// comment = "Add food"
+/// foreign_key_check = true
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MigrationManifest {
+    /// Migrations, in order
+    pub migrations: Vec<MigrationEntry>,
+    /// Repeatable migrations, see [`Migrations::repeatable`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repeatable: Vec<RepeatableEntry>,
+}
+
+/// One migration of a [`MigrationManifest`]. Fields mirror the methods of [`M`] and all but
+/// `up` are optional.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct MigrationEntry {
+    /// See [`M::up`]
+    pub up: String,
+    /// Name of the up hook in the [`HookRegistry`], see [`M::up_with_hook`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_hook: Option<String>,
+    /// See [`M::down`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down: Option<String>,
+    /// Name of the down hook in the [`HookRegistry`], see [`M::down_with_hook`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_hook: Option<String>,
+    /// See [`M::foreign_key_check`]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub foreign_key_check: bool,
+    /// See [`M::comment`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// See [`M::id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// See [`M::depends_on`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// See [`M::allow_lints`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_lints: Vec<LintRule>,
+    /// See [`M::non_destructive`]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub non_destructive: bool,
+    /// See [`M::read_compatible_since`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_compatible_since: Option<usize>,
+}
+
+/// Repeatable migration of a [`MigrationManifest`], see [`Migrations::repeatable`]
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RepeatableEntry {
+    /// Name of the repeatable migration
+    pub name: String,
+    /// SQL of the repeatable migration
+    pub sql: String,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// Hooks referenced by name in a [`MigrationManifest`], as closures can’t be serialized
+///
+/// # Example
+///
+/// ```
+/// use rusqlite_migration::{HookRegistry, MigrationManifest, Migrations};
+///
+/// let hooks = HookRegistry::new().register("seed", |tx: &rusqlite::Transaction| {
+///     tx.execute("INSERT INTO animals (name) VALUES ('dog')", [])?;
+///     Ok(())
+/// });
+/// let manifest: MigrationManifest = serde_json::from_str(r#"{
+///     "migrations": [{"up": "CREATE TABLE animals (name TEXT);", "up_hook": "seed"}]
+/// }"#).unwrap();
+/// let migrations = Migrations::from_manifest(&manifest, &hooks).unwrap();
+/// ```
+#[derive(Default, Clone)]
+#[must_use]
+pub struct HookRegistry {
+    hooks: HashMap<String, Box<dyn MigrationHook>>,
+}
+
+impl HookRegistry {
+    /// Registry without any hook
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a hook under `name`, replacing any hook registered under the same name
+    pub fn register(mut self, name: impl Into<String>, hook: impl MigrationHook + 'static) -> Self {
+        self.hooks.insert(name.into(), hook.clone_box());
+        self
+    }
+
+    fn get(&self, name: &str) -> Result<Box<dyn MigrationHook>> {
+        (self.hooks.get(name))
+            .map(|hook| hook.clone_box())
+            .ok_or_else(|| Error::Manifest(format!("no hook registered under the name “{name}”")))
+    }
+}
+
+impl fmt::Debug for HookRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.hooks.keys().collect();
+        names.sort();
+        f.debug_struct("HookRegistry")
+            .field("hooks", &names)
+            .finish()
+    }
+}
+
+impl<'m> Migrations<'m> {
+    /// Create a set of migrations from a manifest, usually deserialized. Hooks are looked up
+    /// by name in `hooks`. See [`MigrationManifest`] for an example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Manifest`] if a hook is not registered.
+    pub fn from_manifest(manifest: &'m MigrationManifest, hooks: &HookRegistry) -> Result<Self> {
+        let migrations: Self = (manifest.migrations.iter())
+            .map(|entry| {
+                let mut m = M::up(&entry.up)
+                    .allow_lints(&entry.allowed_lints)
+                    .non_destructive_if(entry.non_destructive);
+                m.up_hook = entry.up_hook.as_deref().map(|h| hooks.get(h)).transpose()?;
+                m.down = entry.down.as_deref().into();
+                m.down_hook = (entry.down_hook.as_deref())
+                    .map(|h| hooks.get(h))
+                    .transpose()?;
+                m.foreign_key_check = entry.foreign_key_check;
+                m.comment = entry.comment.as_deref().into();
+                m.id = entry.id.as_deref().into();
+                m.dependencies =
+                    Dependencies::collected(entry.depends_on.iter().map(String::as_str).collect());
+                m.read_compatible_since = entry.read_compatible_since;
+                Ok(m)
+            })
+            .collect::<Result<_>>()?;
+        Ok(
+            (manifest.repeatable.iter()).fold(migrations, |migrations, entry| {
+                migrations.repeatable(entry.name.as_str(), entry.sql.as_str())
+            }),
+        )
+    }
+
+    /// Manifest listing the migrations
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Manifest`] if a migration has a hook, as hooks have no name to be
+    /// referenced by.
+    pub fn to_manifest(&self) -> Result<MigrationManifest> {
+        Ok(MigrationManifest {
+            migrations: (self.ms.iter())
+                .map(MigrationEntry::try_from)
+                .collect::<Result<_>>()?,
+            repeatable: (self.repeatable.iter())
+                .map(|r| RepeatableEntry {
+                    name: r.name.clone().into_owned(),
+                    sql: r.sql.clone().into_owned(),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl M<'_> {
+    const fn non_destructive_if(mut self, non_destructive: bool) -> Self {
+        self.non_destructive = non_destructive;
+        self
+    }
+}
+
+/// Fails with [`Error::Manifest`] if the migration has a hook, as hooks have no name
+impl TryFrom<&M<'_>> for MigrationEntry {
+    type Error = Error;
+
+    fn try_from(m: &M<'_>) -> Result<Self> {
+        if m.up_hook.is_some() || m.down_hook.is_some() {
+            return Err(Error::Manifest(String::from(
+                "migrations with hooks can’t be serialized, as hooks have no name",
+            )));
+        }
+        Ok(MigrationEntry {
+            up: m.up.clone().into_owned(),
+            up_hook: None,
+            down: m.down.as_deref().map(str::to_owned),
+            down_hook: None,
+            foreign_key_check: m.foreign_key_check,
+            comment: m.comment.as_deref().map(str::to_owned),
+            id: m.id.as_deref().map(str::to_owned),
+            depends_on: m.dependencies.iter().map(|&id| String::from(id)).collect(),
+            allowed_lints: m.allowed_lints.to_vec(),
+            non_destructive: m.non_destructive,
+            read_compatible_since: m.read_compatible_since,
+        })
+    }
+}
+
+/// Serialized like a [`MigrationEntry`]. Serialization fails if the migration has a hook.
+impl Serialize for M<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        (MigrationEntry::try_from(self))
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Serialized like a [`MigrationManifest`]. Serialization fails if a migration has a hook.
+impl Serialize for Migrations<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Migrations", 2)?;
+        s.serialize_field("migrations", &*self.ms)?;
+        if self.repeatable.is_empty() {
+            s.skip_field("repeatable")?;
+        } else {
+            s.serialize_field("repeatable", &self.repeatable)?;
+        }
+        s.end()
+    }
+}
+
+/// Serialize a value with its [`fmt::Display`] implementation, for errors that can’t be
+/// serialized otherwise
+pub(crate) fn display<T: fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compose sets of migrations, from several crates for instance
+
+use std::fmt;
+
+use log::warn;
+use rusqlite::{Connection, Transaction};
+
+use crate::{
+    sql::{checksum, quote_string},
+    Error, Migrations, Result, SchemaVersion,
+};
+
+/// Table recording the module, id and checksum of each applied migration, by version
+pub(crate) const MODULES_TABLE: &str = "rusqlite_migration_modules";
+
+/// Migrations of a set registered with [`Migrations::module`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Module {
+    pub(crate) name: String,
+    /// Index of the first migration of the module in the set
+    pub(crate) start: usize,
+    pub(crate) len: usize,
+}
+
+impl<'m> Migrations<'m> {
+    /// Add the migrations of `other` after the ones of this set. Options of `other`, like
+    /// [`Migrations::schema`], are ignored. Its modules are kept, see [`Migrations::module`], and
+    /// so are its [repeatable migrations](Migrations::repeatable), replacing the ones of this set
+    /// with the same name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let core = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    /// let extra = Migrations::new(vec![M::up("CREATE TABLE food (name TEXT);")]);
+    ///
+    /// let migrations = core.append(extra);
+    /// # assert_eq!(migrations, Migrations::new(vec![
+    /// #     M::up("CREATE TABLE animals (name TEXT);"),
+    /// #     M::up("CREATE TABLE food (name TEXT);"),
+    /// # ]));
+    /// ```
+    #[must_use]
+    pub fn append(mut self, other: Migrations<'m>) -> Self {
+        let offset = self.ms.len();
+        self.modules
+            .extend(other.modules.into_iter().map(|module| Module {
+                start: module.start + offset,
+                ..module
+            }));
+        self.ms.to_mut().extend(other.ms.into_owned());
+        for repeatable in other.repeatable {
+            self = self.repeatable(repeatable.name, repeatable.sql);
+        }
+        self
+    }
+
+    /// Add the migrations of `migrations` after the ones of this set, as a module named `name`.
+    /// This lets crates each contribute their own migrations to a single set. Module names
+    /// should be unique.
+    ///
+    /// Modules must only ever append migrations, and be registered in the same order: a
+    /// migration inserted in the middle of a module shifts the versions of all the following
+    /// migrations. To detect this, the module and the checksum of each migration applied are
+    /// recorded in a `rusqlite_migration_modules` table, along with the time it was applied, see
+    /// [`Migrations::status`]. When the migrations defined at the
+    /// applied versions don’t match the recorded ones, [`Migrations::to_latest`] and
+    /// [`Migrations::to_version`] fail with [`Error::MigrationsReordered`], even when there is no
+    /// migration to run, and the database is left untouched. Checksums ignore comments and whitespace.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Error, Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// let core = || Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    ///
+    /// Migrations::new(vec![])
+    ///     .module("core", core())
+    ///     .module("food", Migrations::new(vec![M::up("CREATE TABLE food (name TEXT);")]))
+    ///     .to_latest(&mut conn)
+    ///     .unwrap();
+    ///
+    /// // The food module inserts a migration before its first one
+    /// let migrations = Migrations::new(vec![])
+    ///     .module("core", core())
+    ///     .module(
+    ///         "food",
+    ///         Migrations::new(vec![
+    ///             M::up("CREATE TABLE fruits (name TEXT);"),
+    ///             M::up("CREATE TABLE food (name TEXT);"),
+    ///         ]),
+    ///     );
+    /// assert!(matches!(
+    ///     migrations.to_latest(&mut conn),
+    ///     Err(Error::MigrationsReordered { version: 2, .. })
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn module(mut self, name: impl Into<String>, migrations: Migrations<'m>) -> Self {
+        let start = self.ms.len();
+        self.modules.push(Module {
+            name: name.into(),
+            start,
+            len: migrations.ms.len(),
+        });
+        self.append(migrations)
+    }
+
+    /// Version of the whole set once the first `version` migrations of the module `name` are
+    /// applied, or `None` if there is no such module or it has fewer migrations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, SchemaVersion, M};
+    ///
+    /// let migrations = Migrations::new(vec![])
+    ///     .module("core", Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]))
+    ///     .module("food", Migrations::new(vec![M::up("CREATE TABLE food (name TEXT);")]));
+    ///
+    /// assert_eq!(
+    ///     migrations.module_version("food", 1),
+    ///     Some(SchemaVersion::Inside(2.try_into().unwrap()))
+    /// );
+    /// assert_eq!(migrations.module_version("food", 2), None);
+    /// ```
+    pub fn module_version(&self, name: &str, version: usize) -> Option<SchemaVersion> {
+        let module = self.modules.iter().find(|module| module.name == name)?;
+        (version <= module.len).then(|| self.db_version_to_schema(module.start + version))
+    }
+
+    /// Check that the migrations applied up to `current_version` are still defined at the same
+    /// versions, if they were recorded
+    pub(crate) fn check_modules(&self, conn: &Connection, current_version: usize) -> Result<()> {
+        self.check_ids()?;
+        if !self.table_exists(conn, MODULES_TABLE)? {
+            return Ok(());
+        }
+        let select = format!(
+            "SELECT version, module, module_version, checksum, id FROM {} WHERE version <= ?1 ORDER BY version;",
+            self.qualified(MODULES_TABLE)
+        );
+        let mut stmt = conn
+            .prepare(&select)
+            .map_err(|e| Error::with_sql(e, &select))?;
+        let applied = stmt
+            .query_map([current_version.min(self.ms.len()) as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, i64>(2)? as usize,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })
+            .and_then(Iterator::collect::<rusqlite::Result<Vec<_>>>)
+            .map_err(|e| Error::with_sql(e, &select))?;
+
+        for (version, module, module_version, checksum, id) in applied {
+            let applied = Record {
+                module,
+                module_version,
+                checksum,
+                id,
+            };
+            let defined = self.record(version);
+            if !applied.matches(&defined) {
+                warn!("migration {version} was applied as {applied} but is defined as {defined}");
+                return Err(Error::MigrationsReordered {
+                    version,
+                    applied: applied.to_string(),
+                    defined: defined.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the migrations applied, when modules or ids are used, and forget the ones above the
+    /// new version
+    pub(crate) fn write_modules(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        if !self.records_migrations() && !self.table_exists(tx, MODULES_TABLE)? {
+            return Ok(());
+        }
+        let sql = self.modules_sql(current_version, target_version);
+        tx.execute_batch(&sql).map_err(|e| Error::with_sql(e, &sql))
+    }
+
+    /// Statements recording the migrations from `current_version` to `target_version` and
+    /// forgetting the ones above `target_version`
+    pub(crate) fn modules_sql(&self, current_version: usize, target_version: usize) -> String {
+        let table = self.qualified(MODULES_TABLE);
+        let mut sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table}(version INTEGER PRIMARY KEY, module TEXT, module_version INTEGER NOT NULL, checksum TEXT NOT NULL, applied_at INTEGER, id TEXT);\n\
+            DELETE FROM {table} WHERE version > {target_version};\n"
+        );
+        for version in current_version + 1..=target_version {
+            let Record {
+                module,
+                module_version,
+                checksum,
+                id,
+            } = self.record(version);
+            let module = module
+                .as_deref()
+                .map_or_else(|| String::from("NULL"), quote_string);
+            let id = id
+                .as_deref()
+                .map_or_else(|| String::from("NULL"), quote_string);
+            sql.push_str(&format!(
+                "INSERT OR REPLACE INTO {table}(version, module, module_version, checksum, applied_at, id) VALUES ({version}, {module}, {module_version}, '{checksum}', CAST(strftime('%s', 'now') AS INTEGER), {id});\n"
+            ));
+        }
+        sql
+    }
+
+    /// Record of the migration defined at `version`, starting at 1
+    fn record(&self, version: usize) -> Record {
+        let index = version - 1;
+        let module = (self.modules.iter())
+            .find(|module| (module.start..module.start + module.len).contains(&index));
+        Record {
+            module: module.map(|module| module.name.clone()),
+            module_version: module.map_or(version, |module| index - module.start + 1),
+            checksum: checksum(&self.ms[index].up),
+            id: self.ms[index].id.as_deref().map(str::to_owned),
+        }
+    }
+
+    /// Whether applied migrations are recorded in the modules table
+    pub(crate) fn records_migrations(&self) -> bool {
+        !self.modules.is_empty() || self.ms.iter().any(|m| m.id.is_some())
+    }
+}
+
+/// Row of the modules table
+struct Record {
+    module: Option<String>,
+    module_version: usize,
+    checksum: String,
+    id: Option<String>,
+}
+
+impl Record {
+    /// Whether the applied migration `self` is the `defined` one. Migrations applied before
+    /// being given an id still match.
+    fn matches(&self, defined: &Record) -> bool {
+        self.module == defined.module
+            && self.module_version == defined.module_version
+            && self.checksum == defined.checksum
+            && (self.id.is_none() || self.id == defined.id)
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.module {
+            Some(module) => write!(f, "migration {} of module “{module}”", self.module_version)?,
+            None => write!(f, "migration {} outside of any module", self.module_version)?,
+        }
+        match &self.id {
+            Some(id) => write!(f, " (id “{id}”, checksum {})", self.checksum),
+            None => write!(f, " (checksum {})", self.checksum),
+        }
+    }
+}
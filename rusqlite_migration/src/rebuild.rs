@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rebuild a table following the generalized `ALTER TABLE` procedure of SQLite
+
+use log::debug;
+use rusqlite::Transaction;
+
+use crate::{
+    fk_check::FKCheck,
+    schema::read_raw_schema,
+    sql::{quote_identifier, rename_create_table},
+    Error, HookError, HookResult, M,
+};
+
+/// Change the definition of a table in ways `ALTER TABLE` does not support, following the
+/// [generalized `ALTER TABLE` procedure][procedure] of SQLite.
+///
+/// `create_table_sql` is the new `CREATE TABLE` statement of the table. The name it contains is
+/// ignored, `table` is used instead. This function:
+/// 1. drops all the views and triggers of the database,
+/// 2. creates the new table under a temporary name,
+/// 3. copies the columns shared by the old and new definitions,
+/// 4. drops the old table and renames the new one,
+/// 5. recreates the indexes of the table, the views and the triggers,
+/// 6. checks foreign keys, like [`M::foreign_key_check`].
+///
+/// Indexes, views or triggers referencing a removed column fail to be recreated: drop or change
+/// them before calling this function.
+///
+/// This is meant to be called from a [`crate::MigrationHook`], see also [`M::rebuild_table`].
+///
+/// # Foreign keys
+///
+/// Dropping the old table deletes its rows, which fires `ON DELETE` actions of foreign keys when
+/// `PRAGMA foreign_keys` is ON. Turn it OFF before running the migrations, as explained in
+/// [`M::foreign_key_check`].
+///
+/// # Example
+///
+/// ```
+/// use rusqlite::Transaction;
+/// use rusqlite_migration::{rebuild_table, Migrations, M};
+///
+/// let migrations = Migrations::new(vec![
+///     M::up("CREATE TABLE animals (name TEXT, legs INTEGER);"),
+///     // Make the name mandatory, which ALTER TABLE can’t do
+///     M::up_with_hook("", |tx: &Transaction| {
+///         rebuild_table(tx, "animals", "CREATE TABLE animals (name TEXT NOT NULL, legs INTEGER)")
+///     }),
+/// ]);
+///
+/// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+/// migrations.to_latest(&mut conn).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Returns [`HookError::RusqliteError`] if a statement fails, [`HookError::Hook`] if
+/// `create_table_sql` is not a `CREATE TABLE` statement with column definitions and
+/// [`HookError::ForeignKeyCheck`] if the foreign key check fails.
+///
+/// [procedure]: https://www.sqlite.org/lang_altertable.html#otheralter
+pub fn rebuild_table(tx: &Transaction, table: &str, create_table_sql: &str) -> HookResult {
+    let objects = read_raw_schema(tx).map_err(into_hook_error)?;
+    let new_name = format!("rusqlite_migration_new_{table}");
+    let create_new = rename_create_table(create_table_sql, &new_name).ok_or_else(|| {
+        HookError::Hook(format!(
+            "cannot rebuild table {table}, not a CREATE TABLE statement with columns: {create_table_sql}"
+        ))
+    })?;
+
+    for kind in ["trigger", "view"] {
+        for object in objects.iter().rev().filter(|o| o.kind == kind) {
+            let name = quote_identifier(&object.name);
+            tx.execute_batch(&format!("DROP {} {name}", kind.to_uppercase()))?;
+        }
+    }
+
+    tx.execute_batch(&create_new)?;
+    let shared_columns: Vec<String> = tx
+        .prepare(
+            "SELECT n.name FROM pragma_table_info(?1) AS n
+            JOIN pragma_table_info(?2) AS o USING (name) ORDER BY n.cid",
+        )?
+        .query_map([&new_name, table], |row| row.get::<_, String>(0))?
+        .map(|name| name.map(|name| quote_identifier(&name)))
+        .collect::<Result<_, _>>()?;
+
+    for stmt in copy_statements(table, &new_name, &shared_columns) {
+        debug!("rebuilding table {table}: {stmt}");
+        tx.execute_batch(&stmt)?;
+    }
+
+    // Objects are recreated in their creation order, so that they reference existing objects
+    let recreated = objects.iter().filter(|o| match o.kind.as_str() {
+        "index" => o.table == table,
+        "view" | "trigger" => true,
+        _ => false,
+    });
+    for sql in recreated.filter_map(|o| o.sql.as_deref()) {
+        tx.execute_batch(sql)?;
+    }
+
+    FKCheck::new().validate(tx).map_err(into_hook_error)
+}
+
+/// Statements copying the shared columns from `table` to `new_name`, dropping `table` and
+/// renaming `new_name` to `table`. Columns must already be quoted.
+pub(crate) fn copy_statements(
+    table: &str,
+    new_name: &str,
+    shared_columns: &[String],
+) -> Vec<String> {
+    let (table, new_name) = (quote_identifier(table), quote_identifier(new_name));
+    let mut stmts = Vec::new();
+    if !shared_columns.is_empty() {
+        let columns = shared_columns.join(", ");
+        stmts.push(format!(
+            "INSERT INTO {new_name} ({columns}) SELECT {columns} FROM {table}"
+        ));
+    }
+    stmts.push(format!("DROP TABLE {table}"));
+    stmts.push(format!("ALTER TABLE {new_name} RENAME TO {table}"));
+    stmts
+}
+
+fn into_hook_error(e: Error) -> HookError {
+    match e {
+        Error::RusqliteError { err, .. } => HookError::RusqliteError(err),
+        Error::ForeignKeyCheck(e) => HookError::ForeignKeyCheck(e),
+        e => HookError::Hook(e.to_string()),
+    }
+}
+
+impl M<'_> {
+    /// Create a migration rebuilding `table` with a new definition, `create_table_sql`. See
+    /// [`rebuild_table`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT, legs INTEGER);"),
+    ///     M::rebuild_table("animals", "CREATE TABLE animals (name TEXT NOT NULL, legs INTEGER)")
+    ///         .comment("Make animal names mandatory"),
+    /// ]);
+    /// ```
+    pub fn rebuild_table(table: &'static str, create_table_sql: &'static str) -> Self {
+        Self::up_with_hook("", move |tx: &Transaction| {
+            rebuild_table(tx, table, create_table_sql)
+        })
+    }
+}
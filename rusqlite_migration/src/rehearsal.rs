@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Run pending migrations on a copy of a database
+
+use std::{
+    fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use rusqlite::{backup, Connection};
+
+use crate::{
+    fk_check::FKCheck, sql::quote_identifier, user_version_in, Error, ForeignKeyCheckError,
+    MigrationDefinitionError, MigrationDirection, Migrations, Result,
+};
+
+/// Report of [`Migrations::rehearse`]
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Rehearsal {
+    /// Version of the database before the migrations
+    pub from_version: usize,
+    /// Migrations that were applied successfully, in order
+    pub steps: Vec<RehearsalStep>,
+    /// Error of the migration that failed, if any. Later migrations were not run.
+    pub error: Option<Error>,
+    /// Foreign key violations found after the migrations, whether or not
+    /// [`crate::M::foreign_key_check`] is enabled
+    pub foreign_key_violations: Vec<ForeignKeyCheckError>,
+    /// Time taken by all the migrations
+    pub duration: Duration,
+}
+
+/// Migration applied during a [`Rehearsal`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct RehearsalStep {
+    /// Index of the migration
+    pub migration_index: usize,
+    /// Comment of the migration, see [`crate::M::comment`]
+    pub comment: Option<String>,
+    /// Time taken by the migration
+    pub duration: Duration,
+}
+
+impl Rehearsal {
+    /// Whether the migrations succeeded without any foreign key violation
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.foreign_key_violations.is_empty()
+    }
+}
+
+impl Migrations<'_> {
+    /// Run the pending migrations on an in-memory copy of `conn`, which is left untouched, and
+    /// report how they went. Unlike [`Migrations::validate`], this exercises the migrations
+    /// against actual data.
+    ///
+    /// Like [`Migrations::to_latest`], migrations, repeatable ones included, are applied in a
+    /// single transaction, which is rolled back if one of them fails. Each migration is timed
+    /// separately and foreign keys are checked at the end, before the transaction is committed or
+    /// rolled back. When the migrations target an attached schema (see [`Migrations::schema`]),
+    /// only that schema is copied, and attached to the copy under the same name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute_batch("CREATE TABLE animals (name TEXT); INSERT INTO animals VALUES ('dog'), ('dog');")
+    ///     .unwrap();
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;"),
+    ///     M::up("CREATE UNIQUE INDEX animals_name ON animals(name);").comment("Unique names"),
+    /// ]);
+    ///
+    /// let rehearsal = migrations.rehearse(&conn).unwrap();
+    /// assert!(!rehearsal.is_success());
+    /// assert_eq!(rehearsal.steps.len(), 1); // Only the first migration succeeded
+    /// println!("{rehearsal}");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RusqliteError`] if the database can’t be copied or its version can’t be
+    /// read. Errors of the migrations themselves are given in the report, like the ones of the
+    /// checks made before migrating, such as [`Error::MigrationsReordered`].
+    pub fn rehearse(&self, conn: &Connection) -> Result<Rehearsal> {
+        self.rehearse_on_copy(conn, None)
+    }
+
+    /// Like [`Migrations::rehearse`], but copy the database to a file at `path`. The file is
+    /// overwritten if it exists and kept after the rehearsal, for inspection. When a migration
+    /// fails, the file holds the database as it was before the migrations. This is useful for
+    /// databases too large to fit in memory.
+    ///
+    /// # Errors
+    ///
+    /// See [`Migrations::rehearse`].
+    pub fn rehearse_with_file(
+        &self,
+        conn: &Connection,
+        path: impl AsRef<Path>,
+    ) -> Result<Rehearsal> {
+        self.rehearse_on_copy(conn, Some(path.as_ref()))
+    }
+
+    /// Copy the schema migrated to `path`, or to memory. In the copy, the schema has the same
+    /// name as in `conn`, so that the migrations run unchanged.
+    fn copy(&self, conn: &Connection, path: Option<&Path>) -> Result<Connection> {
+        let schema_name = self.schema_name();
+        let Some(path) = path else {
+            let mut copy = self.open_scratch()?;
+            backup::Backup::new_with_names(conn, schema_name, &mut copy, schema_name)?
+                .run_to_completion(1000, Duration::ZERO, None)?;
+            return Ok(copy);
+        };
+
+        conn.backup(schema_name, path, None)?;
+        match &self.schema {
+            None => Ok(Connection::open(path)?),
+            Some(schema) => {
+                let copy = Connection::open_in_memory()?;
+                let path =
+                    (path.to_str()).ok_or_else(|| rusqlite::Error::InvalidPath(path.to_owned()))?;
+                let attach = format!("ATTACH DATABASE ?1 AS {}", quote_identifier(schema));
+                copy.execute(&attach, [path])
+                    .map_err(|e| Error::with_sql(e, &attach))?;
+                Ok(copy)
+            }
+        }
+    }
+
+    fn rehearse_on_copy(&self, conn: &Connection, path: Option<&Path>) -> Result<Rehearsal> {
+        if self.is_graph() {
+            return self.linearized(conn)?.rehearse_on_copy(conn, path);
+        }
+        if self.ms.is_empty() {
+            return Err(Error::MigrationDefinition(
+                MigrationDefinitionError::NoMigrationsDefined,
+            ));
+        }
+        let mut copy = self.copy(conn, path)?;
+
+        let from_version = user_version_in(&copy, self.schema.as_deref())?;
+        let mut rehearsal = Rehearsal {
+            from_version,
+            steps: Vec::new(),
+            error: None,
+            foreign_key_violations: Vec::new(),
+            duration: Duration::ZERO,
+        };
+        if from_version > self.ms.len() {
+            rehearsal.error = Some(Error::MigrationDefinition(
+                MigrationDefinitionError::DatabaseTooFarAhead,
+            ));
+            return Ok(rehearsal);
+        }
+        let target_version = self.ms.len();
+        // Same checks as the ones of Migrations::to_latest
+        if let Err(e) = (self.check_downgrade(from_version, target_version))
+            .and_then(|()| self.check_modules(&copy, from_version))
+        {
+            rehearsal.error = Some(e);
+            return Ok(rehearsal);
+        }
+
+        let tx = copy.transaction()?;
+        let mut fk_check = FKCheck::for_schema(self.schema.as_deref());
+        let start = Instant::now();
+        for (v, m) in self.ms.iter().enumerate().skip(from_version) {
+            let step_start = Instant::now();
+            if let Err(e) = m.run_up(&tx, &mut fk_check) {
+                rehearsal.error = Some(e.in_migration(
+                    v,
+                    MigrationDirection::Up,
+                    target_version,
+                    m.comment.as_deref(),
+                ));
+                break;
+            }
+            rehearsal.steps.push(RehearsalStep {
+                migration_index: v,
+                comment: m.comment.as_deref().map(str::to_owned),
+                duration: step_start.elapsed(),
+            });
+        }
+        drop(fk_check);
+        if rehearsal.error.is_none() {
+            rehearsal.error = (self.record_version(&tx, from_version, target_version))
+                .and_then(|()| self.run_repeatable(&tx))
+                .err();
+        }
+        rehearsal.duration = start.elapsed();
+
+        match FKCheck::for_schema(self.schema.as_deref()).validate(&tx) {
+            Ok(()) => (),
+            Err(Error::ForeignKeyCheck(violations)) => {
+                rehearsal.foreign_key_violations = violations
+            }
+            Err(e) => return Err(e),
+        }
+        if rehearsal.error.is_none() {
+            tx.commit()?;
+        }
+        info!("Rehearsal done in {:?}", rehearsal.duration);
+
+        Ok(rehearsal)
+    }
+}
+
+impl fmt::Display for RehearsalStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "migration {}", self.migration_index)?;
+        if let Some(comment) = &self.comment {
+            write!(f, " ({comment})")?;
+        }
+        write!(f, ": {:?}", self.duration)
+    }
+}
+
+impl fmt::Display for Rehearsal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rehearsal from version {}", self.from_version)?;
+        for step in &self.steps {
+            writeln!(f, "  {step}")?;
+        }
+        if let Some(e) = &self.error {
+            writeln!(f, "  failed: {e}")?;
+        }
+        for violation in &self.foreign_key_violations {
+            writeln!(f, "  {violation}")?;
+        }
+        write!(f, "total: {:?}", self.duration)
+    }
+}
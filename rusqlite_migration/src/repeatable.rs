@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migrations applied again whenever their SQL changes
+
+use std::{borrow::Cow, collections::HashMap};
+
+use log::{debug, trace};
+use rusqlite::{Connection, Transaction};
+
+use crate::{
+    execute_statements,
+    sql::{checksum, quote_string},
+    Error, Migrations, Result,
+};
+
+/// Table recording the checksum of each repeatable migration applied, by name
+pub(crate) const REPEATABLE_TABLE: &str = "rusqlite_migration_repeatable";
+
+/// Migration registered with [`Migrations::repeatable`], serialized like a
+/// [`crate::RepeatableEntry`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct Repeatable<'m> {
+    pub(crate) name: Cow<'m, str>,
+    pub(crate) sql: Cow<'m, str>,
+}
+
+impl<'m> Migrations<'m> {
+    /// Add a repeatable migration named `name`, replacing any repeatable migration with the same
+    /// name. This suits views and triggers, easier to maintain as their current definition than
+    /// as a history of changes.
+    ///
+    /// [`Migrations::to_latest`] applies repeatable migrations after the other migrations, in the
+    /// same transaction and in the order of their names, when they were never applied or when their SQL changed since they
+    /// last were. Changes to comments and whitespace are ignored. The checksum of each one
+    /// applied is recorded in a `rusqlite_migration_repeatable` table. As a repeatable migration
+    /// runs again on an existing schema, it should first drop what it creates.
+    ///
+    /// Repeatable migrations can also be loaded from a `repeatable` subfolder, see
+    /// [`Migrations::from_directory`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT, legs INTEGER);"),
+    /// ])
+    /// .repeatable(
+    ///     "bipeds",
+    ///     "DROP VIEW IF EXISTS bipeds; CREATE VIEW bipeds AS SELECT name FROM animals WHERE legs = 2;",
+    /// );
+    ///
+    /// migrations.to_latest(&mut conn).unwrap();
+    /// conn.prepare("SELECT * FROM bipeds").unwrap();
+    /// ```
+    #[must_use]
+    pub fn repeatable(
+        mut self,
+        name: impl Into<Cow<'m, str>>,
+        sql: impl Into<Cow<'m, str>>,
+    ) -> Self {
+        let repeatable = Repeatable {
+            name: name.into(),
+            sql: sql.into(),
+        };
+        self.repeatable.retain(|r| r.name != repeatable.name);
+        self.repeatable.push(repeatable);
+        self
+    }
+
+    /// Apply the repeatable migrations that changed, in a transaction
+    pub(crate) fn apply_repeatable(&self, conn: &mut Connection) -> Result<()> {
+        if self.repeatable.is_empty() {
+            return Ok(());
+        }
+        trace!("start repeatable migrations transaction");
+        let tx = conn.transaction()?;
+        self.run_repeatable(&tx)?;
+        tx.commit()?;
+        trace!("committed repeatable migrations transaction");
+        Ok(())
+    }
+
+    /// Apply the repeatable migrations that changed, in an existing transaction
+    pub(crate) fn run_repeatable(&self, tx: &Transaction) -> Result<()> {
+        if self.repeatable.is_empty() {
+            return Ok(());
+        }
+        let table = self.qualified(REPEATABLE_TABLE);
+        let create = format!(
+            "CREATE TABLE IF NOT EXISTS {table}(name TEXT PRIMARY KEY, checksum TEXT NOT NULL, applied_at INTEGER);"
+        );
+        tx.execute_batch(&create)
+            .map_err(|e| Error::with_sql(e, &create))?;
+
+        let select = format!("SELECT name, checksum FROM {table};");
+        let applied: HashMap<String, String> = tx
+            .prepare(&select)
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .map_err(|e| Error::with_sql(e, &select))?;
+
+        let mut repeatable: Vec<&Repeatable> = self.repeatable.iter().collect();
+        repeatable.sort_by(|a, b| a.name.cmp(&b.name));
+        for Repeatable { name, sql } in repeatable {
+            let checksum = checksum(sql);
+            if applied.get(&**name) == Some(&checksum) {
+                trace!("repeatable migration {name} unchanged");
+                continue;
+            }
+            debug!("Running repeatable migration {name}: {sql}");
+            execute_statements(tx, sql).map_err(|e| Error::Repeatable {
+                name: name.to_string(),
+                err: Box::new(e),
+            })?;
+            let record = format!(
+                "INSERT OR REPLACE INTO {table}(name, checksum, applied_at) VALUES ({}, '{checksum}', CAST(strftime('%s', 'now') AS INTEGER));",
+                quote_string(name)
+            );
+            tx.execute_batch(&record)
+                .map_err(|e| Error::with_sql(e, &record))?;
+        }
+        Ok(())
+    }
+}
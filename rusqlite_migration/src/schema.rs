@@ -0,0 +1,474 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read the schema of a database, in a form suitable for comparisons and snapshots
+
+use std::fmt;
+
+use rusqlite::Connection;
+
+use crate::{
+    sql::{normalize_sql, quote_identifier},
+    Error, Result,
+};
+
+mod diff;
+
+pub use diff::{Changes, SchemaDiff, TableDiff};
+
+// Tables of this library, like `rusqlite_migration_compat`, are ignored too
+const SCHEMA_FILTER: &str = "name NOT LIKE 'sqlite\\_%' ESCAPE '\\' \
+    AND tbl_name NOT LIKE 'rusqlite\\_migration\\_%' ESCAPE '\\'";
+
+/// Name of the schema table of an attached database, or of the main one when `schema` is `None`
+fn schema_table(schema: Option<&str>) -> String {
+    match schema {
+        Some(schema) => format!("{}.sqlite_master", quote_identifier(schema)),
+        None => String::from("sqlite_master"),
+    }
+}
+
+/// One row of the `sqlite_schema` table
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct SchemaObject {
+    pub(crate) kind: String,
+    pub(crate) name: String,
+    pub(crate) table: String,
+    pub(crate) sql: Option<String>,
+}
+
+/// Read the content of the `sqlite_schema` table of the database attached as `schema` (`main` by
+/// default), ignoring SQLite internal objects. The SQL text is normalized.
+pub(crate) fn read_schema(conn: &Connection, schema: Option<&str>) -> Result<Vec<SchemaObject>> {
+    let query = format!(
+        "SELECT type, name, tbl_name, sql FROM {} WHERE {SCHEMA_FILTER} ORDER BY type, name;",
+        schema_table(schema)
+    );
+    query_schema(conn, &query, normalize_sql)
+}
+
+/// Read the content of the `sqlite_schema` table in the order the objects were created, ignoring
+/// SQLite internal objects and automatic indexes. The SQL text is left as is, so that it can be
+/// executed again.
+pub(crate) fn read_raw_schema(conn: &Connection) -> Result<Vec<SchemaObject>> {
+    let query = format!(
+        "SELECT type, name, tbl_name, sql FROM sqlite_master \
+        WHERE {SCHEMA_FILTER} AND sql IS NOT NULL ORDER BY rowid;"
+    );
+    query_schema(conn, &query, str::to_owned)
+}
+
+fn query_schema(
+    conn: &Connection,
+    query: &str,
+    map_sql: fn(&str) -> String,
+) -> Result<Vec<SchemaObject>> {
+    let mut stmt = conn.prepare(query).map_err(|e| Error::with_sql(e, query))?;
+    let objects = stmt
+        .query_map([], |row| {
+            Ok(SchemaObject {
+                kind: row.get(0)?,
+                name: row.get(1)?,
+                table: row.get(2)?,
+                sql: row.get::<_, Option<String>>(3)?.map(|sql| map_sql(&sql)),
+            })
+        })
+        .map_err(|e| Error::with_sql(e, query))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::with_sql(e, query))?;
+
+    Ok(objects)
+}
+
+const SELECT_COLUMNS: &str =
+    r#"SELECT name, type, "notnull", dflt_value, pk FROM pragma_table_info(?1, ?2) ORDER BY cid;"#;
+const SELECT_INDEXES: &str =
+    r#"SELECT name, "unique", origin, partial FROM pragma_index_list(?1, ?2) ORDER BY name;"#;
+const SELECT_INDEX_COLUMNS: &str = "SELECT name FROM pragma_index_info(?1, ?2) ORDER BY seqno;";
+const SELECT_FOREIGN_KEYS: &str = r#"SELECT id, "table", "from", "to", on_update, on_delete
+    FROM pragma_foreign_key_list(?1, ?2) ORDER BY id, seq;"#;
+
+/// Schema of a database, as read from the [`sqlite_schema` table][schema_table] and the
+/// [`table_info`][table_info], [`index_list`][index_list], [`index_info`][index_info] and
+/// [`foreign_key_list`][foreign_key_list] pragmas.
+///
+/// Objects are sorted by name and SQL text is normalized, so that two databases with the same
+/// schema compare equal, no matter how the schema was built. The [`Display`](fmt::Display)
+/// implementation gives a readable dump, suitable for snapshot tests.
+///
+/// [schema_table]: https://www.sqlite.org/schematab.html
+/// [table_info]: https://www.sqlite.org/pragma.html#pragma_table_info
+/// [index_list]: https://www.sqlite.org/pragma.html#pragma_index_list
+/// [index_info]: https://www.sqlite.org/pragma.html#pragma_index_info
+/// [foreign_key_list]: https://www.sqlite.org/pragma.html#pragma_foreign_key_list
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct Schema {
+    /// Tables, with their columns, indexes and foreign keys
+    pub tables: Vec<Table>,
+    /// Views
+    pub views: Vec<View>,
+    /// Triggers
+    pub triggers: Vec<Trigger>,
+}
+
+/// A table of the schema
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Table {
+    /// Name of the table
+    pub name: String,
+    /// Normalized SQL text of the table definition
+    pub sql: Option<String>,
+    /// Columns, in the order of the table definition
+    pub columns: Vec<Column>,
+    /// Indexes on the table, including the ones created automatically by SQLite for `UNIQUE`
+    /// and `PRIMARY KEY` constraints
+    pub indexes: Vec<Index>,
+    /// Foreign keys of the table
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// A column of a [`Table`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Column {
+    /// Name of the column
+    pub name: String,
+    /// Declared type, possibly empty
+    pub declared_type: String,
+    /// Whether the column has a `NOT NULL` constraint
+    pub not_null: bool,
+    /// SQL text of the default value, if any
+    pub default: Option<String>,
+    /// Position of the column in the primary key, starting from 1, or 0 if it is not part of the
+    /// primary key
+    pub primary_key: usize,
+}
+
+/// An index of a [`Table`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Index {
+    /// Name of the index
+    pub name: String,
+    /// Whether this is a `UNIQUE` index
+    pub unique: bool,
+    /// How the index was created: `c` for `CREATE INDEX`, `u` for a `UNIQUE` constraint and `pk`
+    /// for a `PRIMARY KEY` constraint
+    pub origin: String,
+    /// Whether this is a partial index
+    pub partial: bool,
+    /// Indexed columns, in order. `None` stands for an expression.
+    pub columns: Vec<Option<String>>,
+    /// Normalized SQL text of the index definition, `None` for automatic indexes
+    pub sql: Option<String>,
+}
+
+/// A foreign key of a [`Table`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct ForeignKey {
+    /// Referenced table
+    pub table: String,
+    /// Columns in the child table
+    pub from: Vec<String>,
+    /// Columns in the parent table. `None` stands for the primary key of the parent table.
+    pub to: Vec<Option<String>>,
+    /// `ON UPDATE` action
+    pub on_update: String,
+    /// `ON DELETE` action
+    pub on_delete: String,
+}
+
+/// A view of the schema
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct View {
+    /// Name of the view
+    pub name: String,
+    /// Normalized SQL text of the view definition
+    pub sql: String,
+}
+
+/// A trigger of the schema
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Trigger {
+    /// Name of the trigger
+    pub name: String,
+    /// Table or view the trigger is attached to
+    pub table: String,
+    /// Normalized SQL text of the trigger definition
+    pub sql: String,
+}
+
+impl Schema {
+    /// Read the schema of the `main` database of the connection. Objects internal to SQLite
+    /// (like `sqlite_sequence`) and tables of this library (named `rusqlite_migration_*`) are
+    /// ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::Schema;
+    ///
+    /// let conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute_batch("CREATE TABLE animals (name TEXT NOT NULL);").unwrap();
+    ///
+    /// let schema = Schema::read(&conn).unwrap();
+    /// assert_eq!(schema.tables[0].name, "animals");
+    /// assert_eq!(schema.to_string(), "table animals\n  column name TEXT NOT NULL\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RusqliteError`] if the schema can't be queried.
+    pub fn read(conn: &Connection) -> Result<Self> {
+        Self::read_in(conn, None)
+    }
+
+    /// Read the schema of the database attached as `schema`, or of the main one when `schema` is
+    /// `None`
+    pub(crate) fn read_in(conn: &Connection, schema: Option<&str>) -> Result<Self> {
+        let schema_name = schema.unwrap_or("main");
+        let mut read = Schema::default();
+        for object in read_schema(conn, schema)? {
+            match object.kind.as_str() {
+                "table" => read.tables.push(read_table(conn, schema_name, object)?),
+                "view" => read.views.push(View {
+                    name: object.name,
+                    sql: object.sql.unwrap_or_default(),
+                }),
+                "trigger" => read.triggers.push(Trigger {
+                    name: object.name,
+                    table: object.table,
+                    sql: object.sql.unwrap_or_default(),
+                }),
+                // Indexes are read with their table
+                _ => (),
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+fn read_table(conn: &Connection, schema_name: &str, object: SchemaObject) -> Result<Table> {
+    let name = object.name;
+    let with_sql = |sql: &'static str| move |e| Error::with_sql(e, sql);
+
+    let columns = conn
+        .prepare(SELECT_COLUMNS)
+        .map_err(with_sql(SELECT_COLUMNS))?
+        .query_map([&name, schema_name], |row| {
+            Ok(Column {
+                name: row.get(0)?,
+                declared_type: row.get(1)?,
+                not_null: row.get(2)?,
+                default: row.get(3)?,
+                primary_key: row.get::<_, u32>(4)? as usize,
+            })
+        })
+        .map_err(with_sql(SELECT_COLUMNS))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(with_sql(SELECT_COLUMNS))?;
+
+    let mut index_columns = conn
+        .prepare(SELECT_INDEX_COLUMNS)
+        .map_err(with_sql(SELECT_INDEX_COLUMNS))?;
+    let indexes = conn
+        .prepare(SELECT_INDEXES)
+        .map_err(with_sql(SELECT_INDEXES))?
+        .query_map([&name, schema_name], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        })
+        .map_err(with_sql(SELECT_INDEXES))?
+        .map(|row| {
+            let (name, unique, origin, partial) = row.map_err(with_sql(SELECT_INDEXES))?;
+            let columns = index_columns
+                .query_map([&name, schema_name], |row| row.get(0))
+                .map_err(with_sql(SELECT_INDEX_COLUMNS))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(with_sql(SELECT_INDEX_COLUMNS))?;
+            let sql = index_sql(conn, schema_name, &name)?;
+            Ok(Index {
+                name,
+                unique,
+                origin,
+                partial,
+                columns,
+                sql,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut foreign_keys: Vec<(i64, ForeignKey)> = Vec::new();
+    let rows = conn
+        .prepare(SELECT_FOREIGN_KEYS)
+        .map_err(with_sql(SELECT_FOREIGN_KEYS))?
+        .query_map([&name, schema_name], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })
+        .map_err(with_sql(SELECT_FOREIGN_KEYS))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(with_sql(SELECT_FOREIGN_KEYS))?;
+    for (id, table, from, to, on_update, on_delete) in rows {
+        match foreign_keys.last_mut() {
+            Some((last_id, fk)) if *last_id == id => {
+                fk.from.push(from);
+                fk.to.push(to);
+            }
+            _ => foreign_keys.push((
+                id,
+                ForeignKey {
+                    table,
+                    from: vec![from],
+                    to: vec![to],
+                    on_update,
+                    on_delete,
+                },
+            )),
+        }
+    }
+    // Foreign key ids depend on the order of definition, make it independent of it
+    let mut foreign_keys: Vec<ForeignKey> = foreign_keys.into_iter().map(|(_, fk)| fk).collect();
+    foreign_keys.sort_by(|a, b| (&a.table, &a.from).cmp(&(&b.table, &b.from)));
+
+    Ok(Table {
+        name,
+        sql: object.sql,
+        columns,
+        indexes,
+        foreign_keys,
+    })
+}
+
+fn index_sql(conn: &Connection, schema_name: &str, name: &str) -> Result<Option<String>> {
+    let select_index_sql = format!(
+        "SELECT sql FROM {} WHERE type = 'index' AND name = ?1;",
+        schema_table(Some(schema_name))
+    );
+    conn.query_row(&select_index_sql, [name], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .map(|sql| sql.map(|sql| normalize_sql(&sql)))
+    .map_err(|e| Error::with_sql(e, &select_index_sql))
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in &self.tables {
+            write!(f, "{table}")?;
+        }
+        for view in &self.views {
+            writeln!(f, "view {}", view.name)?;
+            writeln!(f, "  {}", view.sql)?;
+        }
+        for trigger in &self.triggers {
+            writeln!(f, "trigger {} on {}", trigger.name, trigger.table)?;
+            writeln!(f, "  {}", trigger.sql)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "table {}", self.name)?;
+        for column in &self.columns {
+            writeln!(f, "  column {column}")?;
+        }
+        for fk in &self.foreign_keys {
+            writeln!(f, "  {fk}")?;
+        }
+        for index in &self.indexes {
+            writeln!(f, "  {index}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.declared_type.is_empty() {
+            write!(f, " {}", self.declared_type)?;
+        }
+        if self.not_null {
+            write!(f, " NOT NULL")?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT {default}")?;
+        }
+        match self.primary_key {
+            0 => Ok(()),
+            1 => write!(f, " PRIMARY KEY"),
+            n => write!(f, " PRIMARY KEY #{n}"),
+        }
+    }
+}
+
+impl fmt::Display for Index {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unique = if self.unique { "unique " } else { "" };
+        let partial = if self.partial { "partial " } else { "" };
+        write!(f, "{unique}{partial}index {} (", self.name)?;
+        for (i, column) in self.columns.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            write!(f, "{sep}{}", column.as_deref().unwrap_or("<expression>"))?;
+        }
+        write!(f, ")")?;
+        if let Some(sql) = &self.sql {
+            write!(f, ": {sql}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "foreign key ({}) references {}({})",
+            self.from.join(", "),
+            self.table,
+            self.to
+                .iter()
+                .map(|c| c.as_deref().unwrap_or("<primary key>"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        if self.on_update != "NO ACTION" {
+            write!(f, " on update {}", self.on_update)?;
+        }
+        if self.on_delete != "NO ACTION" {
+            write!(f, " on delete {}", self.on_delete)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare two schemas
+
+use std::fmt;
+
+use rusqlite::Connection;
+
+use super::{Column, ForeignKey, Index, Schema, Table, Trigger, View};
+use crate::Result;
+
+/// Objects added, removed or changed between two schemas
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Changes<T> {
+    /// Objects only present in the new schema
+    pub added: Vec<T>,
+    /// Objects only present in the old schema
+    pub removed: Vec<T>,
+    /// Objects present in both schemas under the same name, but with a different definition, as
+    /// `(old, new)`
+    pub changed: Vec<(T, T)>,
+}
+
+impl<T> Default for Changes<T> {
+    fn default() -> Self {
+        Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl<T> Changes<T> {
+    /// Whether there is no change at all
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Difference between two [`Schema`]s, see [`Schema::diff`]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct SchemaDiff {
+    /// Tables added or removed. Tables present in both schemas are in
+    /// [`SchemaDiff::changed_tables`].
+    pub tables: Changes<Table>,
+    /// Tables present in both schemas, with a different definition
+    pub changed_tables: Vec<TableDiff>,
+    /// Views added, removed or changed
+    pub views: Changes<View>,
+    /// Triggers added, removed or changed
+    pub triggers: Changes<Trigger>,
+}
+
+/// Difference between two versions of the same table
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct TableDiff {
+    /// Name of the table
+    pub name: String,
+    /// Columns added, removed or changed
+    pub columns: Changes<Column>,
+    /// Indexes added, removed or changed
+    pub indexes: Changes<Index>,
+    /// Foreign keys added or removed. Foreign keys have no name, so a modified foreign key
+    /// appears as removed and added.
+    pub foreign_keys: Changes<ForeignKey>,
+    /// Normalized SQL of the table definition, as `(old, new)`, if it changed. The SQL may change
+    /// without any other difference being reported, for instance when a `CHECK` constraint is
+    /// altered.
+    pub sql: Option<(Option<String>, Option<String>)>,
+}
+
+impl SchemaDiff {
+    /// Read the schemas of two connections and compute the changes to go from `old` to `new`.
+    /// See [`Schema::diff`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::RusqliteError`] if one of the schemas can't be queried.
+    pub fn between(old: &Connection, new: &Connection) -> Result<Self> {
+        Ok(Schema::read(old)?.diff(&Schema::read(new)?))
+    }
+
+    /// Whether the two schemas are the same
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+            && self.changed_tables.is_empty()
+            && self.views.is_empty()
+            && self.triggers.is_empty()
+    }
+}
+
+impl Schema {
+    /// Compute the changes to go from `self` to `new`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::Schema;
+    ///
+    /// let conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// conn.execute_batch("CREATE TABLE animals (name TEXT);").unwrap();
+    /// let old = Schema::read(&conn).unwrap();
+    ///
+    /// conn.execute_batch("ALTER TABLE animals ADD COLUMN age INTEGER;").unwrap();
+    /// let new = Schema::read(&conn).unwrap();
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.changed_tables[0].columns.added[0].name, "age");
+    /// assert!(new.diff(&new).is_empty());
+    /// ```
+    pub fn diff(&self, new: &Schema) -> SchemaDiff {
+        let (tables, both) = changes_by_name(&self.tables, &new.tables, |t| &t.name);
+        let changed_tables = both
+            .into_iter()
+            .filter_map(|(old, new)| table_diff(old, new))
+            .collect();
+
+        SchemaDiff {
+            tables,
+            changed_tables,
+            views: named_changes(&self.views, &new.views, |v| &v.name),
+            triggers: named_changes(&self.triggers, &new.triggers, |t| &t.name),
+        }
+    }
+}
+
+fn table_diff(old: &Table, new: &Table) -> Option<TableDiff> {
+    if old == new {
+        return None;
+    }
+
+    let mut foreign_keys = Changes::default();
+    foreign_keys.removed = (old.foreign_keys.iter())
+        .filter(|fk| !new.foreign_keys.contains(fk))
+        .cloned()
+        .collect();
+    foreign_keys.added = (new.foreign_keys.iter())
+        .filter(|fk| !old.foreign_keys.contains(fk))
+        .cloned()
+        .collect();
+
+    Some(TableDiff {
+        name: new.name.clone(),
+        columns: named_changes(&old.columns, &new.columns, |c| &c.name),
+        indexes: named_changes(&old.indexes, &new.indexes, |i| &i.name),
+        foreign_keys,
+        sql: (old.sql != new.sql).then(|| (old.sql.clone(), new.sql.clone())),
+    })
+}
+
+/// Split objects in added and removed ones, returning the pairs of objects present in both
+fn changes_by_name<'a, T: Clone>(
+    old: &'a [T],
+    new: &'a [T],
+    name: impl Fn(&T) -> &String,
+) -> (Changes<T>, Vec<(&'a T, &'a T)>) {
+    let mut changes = Changes::default();
+    let mut both = Vec::new();
+    for o in old {
+        match new.iter().find(|n| name(n) == name(o)) {
+            Some(n) => both.push((o, n)),
+            None => changes.removed.push(o.clone()),
+        }
+    }
+    changes.added = (new.iter())
+        .filter(|n| !old.iter().any(|o| name(o) == name(n)))
+        .cloned()
+        .collect();
+
+    (changes, both)
+}
+
+fn named_changes<T: Clone + PartialEq>(
+    old: &[T],
+    new: &[T],
+    name: impl Fn(&T) -> &String,
+) -> Changes<T> {
+    let (mut changes, both) = changes_by_name(old, new, name);
+    changes.changed = both
+        .into_iter()
+        .filter(|(o, n)| o != n)
+        .map(|(o, n)| (o.clone(), n.clone()))
+        .collect();
+    changes
+}
+
+fn fmt_changes<T>(
+    f: &mut fmt::Formatter<'_>,
+    indent: &str,
+    kind: &str,
+    changes: &Changes<T>,
+    display: impl Fn(&T) -> String,
+) -> fmt::Result {
+    for t in &changes.removed {
+        writeln!(f, "{indent}- {kind}{}", display(t))?;
+    }
+    for t in &changes.added {
+        writeln!(f, "{indent}+ {kind}{}", display(t))?;
+    }
+    for (old, new) in &changes.changed {
+        writeln!(f, "{indent}- {kind}{}", display(old))?;
+        writeln!(f, "{indent}+ {kind}{}", display(new))?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_changes(f, "", "table ", &self.tables, |t| t.name.clone())?;
+        for table in &self.changed_tables {
+            writeln!(f, "~ table {}", table.name)?;
+            fmt_changes(f, "  ", "column ", &table.columns, Column::to_string)?;
+            fmt_changes(f, "  ", "", &table.indexes, Index::to_string)?;
+            fmt_changes(f, "  ", "", &table.foreign_keys, ForeignKey::to_string)?;
+            if let Some((old, new)) = &table.sql {
+                let none = String::from("<none>");
+                writeln!(f, "  - sql {}", old.as_ref().unwrap_or(&none))?;
+                writeln!(f, "  + sql {}", new.as_ref().unwrap_or(&none))?;
+            }
+        }
+        fmt_changes(f, "", "view ", &self.views, |v| v.sql.clone())?;
+        fmt_changes(f, "", "trigger ", &self.triggers, |t| t.sql.clone())
+    }
+}
@@ -0,0 +1,371 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal SQL tokenizer, just enough to find statements, names and column definitions in the
+//! SQL of migrations. It does not validate the SQL, SQLite does.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum TokenKind {
+    /// Keyword, unquoted identifier or number
+    Word,
+    /// Identifier quoted with `"`, `` ` `` or `[]`
+    Quoted,
+    /// String literal
+    String,
+    /// Any other character, like `(` or `;`
+    Punct,
+    Comment,
+    Whitespace,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    /// Byte offset of the token in the SQL text
+    pub(crate) start: usize,
+}
+
+impl Token<'_> {
+    pub(crate) fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Whether the token is not whitespace nor a comment
+    pub(crate) fn is_significant(&self) -> bool {
+        !matches!(self.kind, TokenKind::Comment | TokenKind::Whitespace)
+    }
+
+    /// Case insensitive comparison with a keyword
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub(crate) fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct && self.text.starts_with(c)
+    }
+
+    /// Name of the identifier, without quotes
+    pub(crate) fn identifier(&self) -> String {
+        match self.kind {
+            TokenKind::Quoted if self.text.starts_with('[') => {
+                self.text[1..self.text.len() - 1].to_owned()
+            }
+            TokenKind::Quoted => {
+                let quote = &self.text[..1];
+                let inner = &self.text[1..self.text.len().max(2) - 1];
+                inner.replace(&quote.repeat(2), quote)
+            }
+            _ => self.text.to_owned(),
+        }
+    }
+}
+
+/// Split the SQL text in tokens. Unterminated strings, identifiers or comments extend to the end
+/// of the text.
+pub(crate) fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let bytes = sql.as_bytes();
+    let mut start = 0;
+
+    while start < sql.len() {
+        let rest = &sql[start..];
+        let c = rest.chars().next().expect("rest is not empty");
+        let (kind, len) = match c {
+            c if c.is_whitespace() => (
+                TokenKind::Whitespace,
+                rest.find(|c: char| !c.is_whitespace())
+                    .unwrap_or(rest.len()),
+            ),
+            '-' if rest.starts_with("--") => {
+                (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+            }
+            '/' if rest.starts_with("/*") => (
+                TokenKind::Comment,
+                rest[2..].find("*/").map_or(rest.len(), |i| i + 4),
+            ),
+            '\'' => (TokenKind::String, quoted_len(bytes, start, b'\'')),
+            '"' | '`' => (TokenKind::Quoted, quoted_len(bytes, start, c as u8)),
+            '[' => (
+                TokenKind::Quoted,
+                rest.find(']').map_or(rest.len(), |i| i + 1),
+            ),
+            c if is_word_char(c) => (
+                TokenKind::Word,
+                rest.find(|c: char| !is_word_char(c) && c != '$')
+                    .unwrap_or(rest.len()),
+            ),
+            c => (TokenKind::Punct, c.len_utf8()),
+        };
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+            start,
+        });
+        start += len;
+    }
+
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || !c.is_ascii()
+}
+
+/// Length of a token delimited by `quote`, where a doubled `quote` is an escaped one
+fn quoted_len(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1 - start;
+        }
+        i += 1;
+    }
+    bytes.len() - start
+}
+
+/// Whether the text of a quoted identifier can be written without quotes
+fn is_plain_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quote an identifier with double quotes, escaping the ones it contains
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a string literal
+pub(crate) fn quote_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Make the SQL stored by SQLite comparable, regardless of the way it was edited. For instance,
+/// `ALTER TABLE … DROP COLUMN` may leave some extra whitespace and `ALTER TABLE … RENAME TO`
+/// quotes the new name.
+///
+/// Comments are removed, whitespace is collapsed and removed around parenthesis and commas. Quotes
+/// around identifiers are removed when they are not needed.
+pub(crate) fn normalize_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut pending_space = false;
+
+    for token in tokenize(sql) {
+        if !token.is_significant() {
+            pending_space = true;
+            continue;
+        }
+
+        let glued =
+            [')', '(', ',', ';'].iter().any(|&c| token.is_punct(c)) || out.ends_with(['(', ',']);
+        if pending_space && !out.is_empty() && !glued {
+            out.push(' ');
+        }
+        pending_space = false;
+
+        match token.kind {
+            TokenKind::Quoted if is_plain_identifier(&token.identifier()) => {
+                out.push_str(&token.identifier())
+            }
+            _ => out.push_str(token.text),
+        }
+    }
+
+    out
+}
+
+/// Item between the parenthesis of a `CREATE TABLE` statement
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum TableItem<'a> {
+    /// Column definition, with the name of the column and the whole definition
+    Column { name: String, definition: &'a str },
+    /// Table constraint, like `PRIMARY KEY (a, b)`
+    Constraint(&'a str),
+}
+
+/// Parsed `CREATE TABLE` statement
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct CreateTable<'a> {
+    /// Token holding the name of the table (without the schema name)
+    pub(crate) name: Token<'a>,
+    pub(crate) items: Vec<TableItem<'a>>,
+}
+
+/// Parse a `CREATE TABLE` statement, returns `None` if the statement is not a `CREATE TABLE` with
+/// a list of column definitions (`CREATE TABLE … AS SELECT` isn’t supported for instance).
+pub(crate) fn parse_create_table(sql: &str) -> Option<CreateTable<'_>> {
+    let tokens: Vec<Token> = tokenize(sql)
+        .into_iter()
+        .filter(Token::is_significant)
+        .collect();
+    let mut i = 0;
+    let mut expect = |keyword: &str| {
+        let found = tokens.get(i).is_some_and(|t| t.is_keyword(keyword));
+        if found {
+            i += 1;
+        }
+        found
+    };
+
+    if !expect("CREATE") {
+        return None;
+    }
+    let _ = expect("TEMP") || expect("TEMPORARY");
+    if !expect("TABLE") {
+        return None;
+    }
+    if expect("IF") && !(expect("NOT") && expect("EXISTS")) {
+        return None;
+    }
+
+    let mut name = *tokens.get(i)?;
+    i += 1;
+    if tokens.get(i)?.is_punct('.') {
+        name = *tokens.get(i + 1)?;
+        i += 2;
+    }
+    if !tokens.get(i)?.is_punct('(') {
+        return None;
+    }
+
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut item_tokens: Vec<&Token> = Vec::new();
+    for token in &tokens[i + 1..] {
+        if depth == 0 && (token.is_punct(',') || token.is_punct(')')) {
+            let first = item_tokens.first()?;
+            let last = item_tokens.last()?;
+            let definition = &sql[first.start..last.end()];
+            let is_constraint = ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+                .iter()
+                .any(|k| first.is_keyword(k));
+            items.push(if is_constraint {
+                TableItem::Constraint(definition)
+            } else {
+                TableItem::Column {
+                    name: first.identifier(),
+                    definition,
+                }
+            });
+            item_tokens.clear();
+            if token.is_punct(')') {
+                return Some(CreateTable { name, items });
+            }
+            continue;
+        }
+        if token.is_punct('(') {
+            depth += 1;
+        } else if token.is_punct(')') {
+            depth -= 1;
+        }
+        item_tokens.push(token);
+    }
+
+    None
+}
+
+/// Replace the name of the table in a `CREATE TABLE` statement
+pub(crate) fn rename_create_table(sql: &str, new_name: &str) -> Option<String> {
+    let create = parse_create_table(sql)?;
+    Some(format!(
+        "{}{}{}",
+        &sql[..create.name.start],
+        quote_identifier(new_name),
+        &sql[create.name.end()..]
+    ))
+}
+
+/// Statement of a SQL text, see [`split_statements`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Statement<'a> {
+    /// Text of the statement, from its first significant token to its semicolon, included
+    pub(crate) text: &'a str,
+    /// Byte offset of the statement in the SQL text
+    pub(crate) start: usize,
+}
+
+/// Split a SQL text in statements, like `sqlite3_complete`. Semicolons in the body of a
+/// `CREATE TRIGGER` statement don’t end the statement, only the one after the last `END` does.
+/// Comments and whitespace between statements are skipped.
+pub(crate) fn split_statements(sql: &str) -> Vec<Statement<'_>> {
+    let mut statements = Vec::new();
+    // First significant tokens of the current statement, to detect `CREATE TRIGGER`
+    let mut head: Vec<Token> = Vec::new();
+    let mut in_trigger = false;
+    // Depth of BEGIN … END and CASE … END blocks in a trigger
+    let mut depth = 0usize;
+
+    for token in tokenize(sql).into_iter().filter(Token::is_significant) {
+        if head.len() < 3 {
+            head.push(token);
+            in_trigger = match head.as_slice() {
+                [create, trigger, ..] if create.is_keyword("CREATE") => {
+                    trigger.is_keyword("TRIGGER")
+                        || ((trigger.is_keyword("TEMP") || trigger.is_keyword("TEMPORARY"))
+                            && head.get(2).is_some_and(|t| t.is_keyword("TRIGGER")))
+                }
+                _ => false,
+            };
+        }
+        if in_trigger && (token.is_keyword("BEGIN") || token.is_keyword("CASE")) {
+            depth += 1;
+        } else if in_trigger && token.is_keyword("END") {
+            depth = depth.saturating_sub(1);
+        }
+
+        if token.is_punct(';') && depth == 0 {
+            let start = head[0].start;
+            statements.push(Statement {
+                text: &sql[start..token.end()],
+                start,
+            });
+            head.clear();
+            in_trigger = false;
+        }
+    }
+    if let Some(first) = head.first() {
+        statements.push(Statement {
+            text: sql[first.start..].trim_end(),
+            start: first.start,
+        });
+    }
+
+    statements
+}
+
+/// Line and column, both starting at 1, of a byte offset in a text. Columns count characters.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Checksum of the SQL of a migration, stable across versions of Rust and insensitive to
+/// comments and whitespace, see [`normalize_sql`]. This is the 64 bits FNV-1a hash of the
+/// normalized SQL, in hexadecimal.
+pub(crate) fn checksum(sql: &str) -> String {
+    let hash = (normalize_sql(sql).bytes()).fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! State of each migration in a database
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::Connection;
+
+use crate::{modules::MODULES_TABLE, user_version_in, Error, Migrations, Result, SchemaVersion};
+
+/// Report of [`Migrations::status`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct Status {
+    /// Version of the database
+    pub version: SchemaVersion,
+    /// State of each migration of the set, in order
+    pub migrations: Vec<MigrationStatus>,
+    /// Versions of the database beyond the last migration of the set, applied by a newer
+    /// version of the program for instance. See [`Migrations::store_down_migrations`] to revert
+    /// them.
+    pub unknown_versions: Vec<usize>,
+}
+
+/// State of a migration, see [`Status`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct MigrationStatus {
+    /// Index of the migration
+    pub index: usize,
+    /// Comment of the migration, see [`crate::M::comment`]
+    pub comment: Option<String>,
+    /// Id of the migration, see [`crate::M::id`]
+    pub id: Option<String>,
+    /// Whether the migration is applied to the database
+    pub applied: bool,
+    /// Whether the migration has a down migration
+    pub reversible: bool,
+    /// Whether the migration has an up or a down hook
+    pub has_hooks: bool,
+    /// Whether foreign keys are checked after the migration, see
+    /// [`crate::M::foreign_key_check`]
+    pub foreign_key_check: bool,
+    /// Time the migration was applied, when recorded in the database. Applied migrations are
+    /// recorded when [`Migrations::module`] or [`crate::M::id`] are used.
+    pub applied_at: Option<SystemTime>,
+}
+
+impl Status {
+    /// Migrations not applied yet
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus> {
+        self.migrations.iter().filter(|m| !m.applied)
+    }
+}
+
+impl Migrations<'_> {
+    /// State of each migration in the database: whether it is applied and, when recorded, when
+    /// it was. Versions of the database beyond the defined migrations are listed too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    ///     M::up("CREATE TABLE food (name TEXT);").comment("Food"),
+    /// ]);
+    /// migrations.to_version(&mut conn, 1).unwrap();
+    ///
+    /// let status = migrations.status(&conn).unwrap();
+    /// assert!(status.migrations[0].applied);
+    /// assert!(status.migrations[0].reversible);
+    /// assert_eq!(status.pending().count(), 1);
+    /// println!("{status}");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RusqliteError`] or [`Error::InvalidUserVersion`] if the version or the
+    /// records of the database can’t be read.
+    pub fn status(&self, conn: &Connection) -> Result<Status> {
+        if self.is_graph() {
+            return self.linearized(conn)?.status(conn);
+        }
+        let db_version = user_version_in(conn, self.schema.as_deref())?;
+        let applied_at = self.applied_at(conn)?;
+
+        let migrations = (self.ms.iter().enumerate())
+            .map(|(index, m)| {
+                let applied = index < db_version;
+                MigrationStatus {
+                    index,
+                    comment: m.comment.as_deref().map(str::to_owned),
+                    id: m.id.as_deref().map(str::to_owned),
+                    applied,
+                    reversible: m.down.is_some(),
+                    has_hooks: m.up_hook.is_some() || m.down_hook.is_some(),
+                    foreign_key_check: m.foreign_key_check,
+                    applied_at: applied_at
+                        .iter()
+                        .find(|(version, _)| *version == index + 1)
+                        .filter(|_| applied)
+                        .map(|(_, time)| *time),
+                }
+            })
+            .collect();
+
+        Ok(Status {
+            version: self.db_version_to_schema(db_version),
+            migrations,
+            unknown_versions: (self.ms.len() + 1..=db_version).collect(),
+        })
+    }
+
+    /// Time each version was applied, when recorded
+    fn applied_at(&self, conn: &Connection) -> Result<Vec<(usize, SystemTime)>> {
+        if !self.table_exists(conn, MODULES_TABLE)? {
+            return Ok(Vec::new());
+        }
+        let select = format!(
+            "SELECT version, applied_at FROM {} WHERE applied_at IS NOT NULL ORDER BY version;",
+            self.qualified(MODULES_TABLE)
+        );
+        let mut stmt = conn
+            .prepare(&select)
+            .map_err(|e| Error::with_sql(e, &select))?;
+        stmt.query_map([], |row| {
+            let version: i64 = row.get(0)?;
+            let applied_at: i64 = row.get(1)?;
+            Ok((
+                version as usize,
+                UNIX_EPOCH + Duration::from_secs(applied_at.max(0) as u64),
+            ))
+        })
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::with_sql(e, &select))
+    }
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.applied { "applied" } else { "pending" };
+        write!(f, "migration {}", self.index)?;
+        if let Some(id) = &self.id {
+            write!(f, " “{id}”")?;
+        }
+        write!(f, " [{state}]")?;
+        if let Some(comment) = &self.comment {
+            write!(f, " ({comment})")?;
+        }
+        if let Some(secs) = (self.applied_at)
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+        {
+            write!(f, ", applied at {secs} (Unix time)")?;
+        }
+        if !self.reversible {
+            write!(f, ", irreversible")?;
+        }
+        if self.has_hooks {
+            write!(f, ", hooks")?;
+        }
+        if self.foreign_key_check {
+            write!(f, ", foreign key check")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "database at version {}", self.version)?;
+        for m in &self.migrations {
+            write!(f, "\n  {m}")?;
+        }
+        match self.unknown_versions.as_slice() {
+            [] => (),
+            [version] => write!(f, "\n  version {version} is unknown to this program")?,
+            [first, .., last] => write!(
+                f,
+                "\n  versions {first} to {last} are unknown to this program"
+            )?,
+        }
+        Ok(())
+    }
+}
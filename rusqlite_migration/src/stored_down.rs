@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Down migrations stored in the database, to revert migrations unknown to older programs
+
+use log::{debug, info};
+use rusqlite::{OptionalExtension, Transaction};
+
+use crate::{
+    execute_statements, sql::quote_string, Error, MigrationDefinitionError, MigrationDirection,
+    Migrations, Result,
+};
+
+/// Table holding the down migrations of the applied migrations, by version
+pub(crate) const DOWN_TABLE: &str = "rusqlite_migration_down";
+
+impl Migrations<'_> {
+    /// Store the SQL of the down migrations in the database as migrations are applied, in a
+    /// `rusqlite_migration_down` table. Programs knowing fewer migrations, typically older
+    /// versions of the program, can then revert the migrations they don’t know, instead of
+    /// failing with [`crate::MigrationDefinitionError::DatabaseTooFarAhead`].
+    /// [`Migrations::to_latest`] and [`Migrations::to_version`] use the stored down migrations
+    /// when this option is enabled.
+    ///
+    /// Down hooks can’t be stored: migrations with one can only be reverted by programs knowing
+    /// them. Foreign keys are not checked when running stored down migrations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusqlite_migration::{Migrations, M};
+    ///
+    /// let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    /// // Newer version of the program
+    /// Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    ///     M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+    /// ])
+    /// .store_down_migrations()
+    /// .to_latest(&mut conn)
+    /// .unwrap();
+    ///
+    /// // Older version of the program, reverting the second migration
+    /// let migrations = Migrations::new(vec![
+    ///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    /// ])
+    /// .store_down_migrations();
+    /// migrations.to_latest(&mut conn).unwrap();
+    /// # assert_eq!(migrations.current_version(&conn).unwrap(), rusqlite_migration::SchemaVersion::Inside(1.try_into().unwrap()));
+    /// ```
+    #[must_use]
+    pub fn store_down_migrations(mut self) -> Self {
+        self.store_down = true;
+        self
+    }
+
+    /// Keep the stored down migrations in line with the new version of the database: store
+    /// the ones of the migrations just applied, if enabled, and forget the ones above the new
+    /// version. Stale down migrations are removed even when the option is disabled.
+    pub(crate) fn write_down_migrations(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        if !self.store_down && !self.table_exists(tx, DOWN_TABLE)? {
+            return Ok(());
+        }
+        let sql = self.down_migrations_sql(current_version, target_version);
+        tx.execute_batch(&sql).map_err(|e| Error::with_sql(e, &sql))
+    }
+
+    /// Statements storing the down migrations from `current_version` to `target_version`, if
+    /// enabled, and forgetting the ones above `target_version`
+    pub(crate) fn down_migrations_sql(
+        &self,
+        current_version: usize,
+        target_version: usize,
+    ) -> String {
+        let table = self.qualified(DOWN_TABLE);
+        let mut sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table}(version INTEGER PRIMARY KEY, sql TEXT);\n\
+            DELETE FROM {table} WHERE version > {target_version};\n"
+        );
+        if self.store_down {
+            for v in current_version..target_version {
+                let m = &self.ms[v];
+                let down = (m.down.as_deref())
+                    .filter(|_| m.down_hook.is_none())
+                    .map_or_else(|| String::from("NULL"), quote_string);
+                sql.push_str(&format!(
+                    "INSERT OR REPLACE INTO {table}(version, sql) VALUES ({}, {down});\n",
+                    v + 1
+                ));
+            }
+        }
+        sql
+    }
+
+    /// Revert the migrations from `current_version` down to the last known one, with the
+    /// stored down migrations
+    pub(crate) fn revert_stored(
+        &self,
+        tx: &Transaction,
+        current_version: usize,
+        target_version: usize,
+    ) -> Result<()> {
+        let too_far_ahead =
+            || Error::MigrationDefinition(MigrationDefinitionError::DatabaseTooFarAhead);
+        if !self.table_exists(tx, DOWN_TABLE)? {
+            return Err(too_far_ahead());
+        }
+        info!(
+            "reverting unknown migrations {}..{current_version} with stored down migrations",
+            self.ms.len()
+        );
+        let select = format!(
+            "SELECT sql FROM {} WHERE version = ?1;",
+            self.qualified(DOWN_TABLE)
+        );
+        for version in (self.ms.len() + 1..=current_version).rev() {
+            let down: Option<String> = tx
+                .query_row(&select, [version as i64], |row| row.get(0))
+                .optional()
+                .map_err(|e| Error::with_sql(e, &select))?
+                .flatten();
+            let Some(down) = down else {
+                debug!("no stored down migration for version {version}");
+                return Err(too_far_ahead());
+            };
+            debug!("Running: {down}");
+            execute_statements(tx, &down).map_err(|e| {
+                e.in_migration(version - 1, MigrationDirection::Down, target_version, None)
+            })?;
+        }
+        Ok(())
+    }
+}
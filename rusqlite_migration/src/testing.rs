@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities to test migrations, in particular that they preserve and transform data as
+//! expected.
+//!
+//! # Example
+//!
+//! ```
+//! use rusqlite_migration::{testing::MigrationTest, Migrations, M};
+//!
+//! let migrations = Migrations::new(vec![
+//!     M::up("CREATE TABLE animals (name TEXT, legs INTEGER);"),
+//!     M::up("ALTER TABLE animals ADD COLUMN wings INTEGER; UPDATE animals SET wings = 4 - legs;")
+//!         .comment("Count wings"),
+//! ]);
+//!
+//! let mut test = MigrationTest::at_version(&migrations, 1);
+//! test.insert("animals", &[("name", &"dog"), ("legs", &4)])
+//!     .insert("animals", &[("name", &"bird"), ("legs", &2)]);
+//!
+//! test.up();
+//! let wings: Vec<i64> = test.query_values("SELECT wings FROM animals ORDER BY name", []);
+//! assert_eq!(wings, vec![2, 0]);
+//! ```
+
+mod fuzz;
+
+pub use fuzz::{FuzzFailure, FuzzStep, Fuzzer};
+
+use rusqlite::{types::FromSql, Connection, Params, Row, ToSql};
+
+use crate::{sql::quote_identifier, user_version_in, Migrations, Result};
+
+/// In-memory database, migrated step by step by the test. When the migrations target an attached
+/// schema (see [`Migrations::schema`]), an in-memory database is attached under that name. Methods panic with a message naming
+/// the migration involved, which makes them convenient in tests.
+#[derive(Debug)]
+pub struct MigrationTest<'a, 'm> {
+    migrations: &'a Migrations<'m>,
+    conn: Connection,
+}
+
+impl<'a, 'm> MigrationTest<'a, 'm> {
+    /// Open an empty in-memory database, at version 0
+    ///
+    /// # Panics
+    ///
+    /// Panics if the database can’t be opened.
+    #[track_caller]
+    pub fn new(migrations: &'a Migrations<'m>) -> Self {
+        let conn = match migrations.open_scratch() {
+            Ok(conn) => conn,
+            Err(e) => panic!("cannot open an in-memory database: {e}"),
+        };
+        Self { migrations, conn }
+    }
+
+    /// Open an in-memory database and migrate it to `version`
+    ///
+    /// # Panics
+    ///
+    /// Panics if a migration fails.
+    #[track_caller]
+    pub fn at_version(migrations: &'a Migrations<'m>, version: usize) -> Self {
+        let mut test = Self::new(migrations);
+        test.to_version(version);
+        test
+    }
+
+    /// Current version of the database
+    ///
+    /// # Panics
+    ///
+    /// Panics if the version can’t be read.
+    #[track_caller]
+    pub fn version(&self) -> usize {
+        match user_version_in(&self.conn, self.migrations.schema.as_deref()) {
+            Ok(version) => version,
+            Err(e) => panic!("cannot read the version: {e}"),
+        }
+    }
+
+    /// Migrate the database to `version`, one migration at a time, like
+    /// [`Migrations::to_version`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a migration fails, with a message naming that migration.
+    #[track_caller]
+    pub fn to_version(&mut self, version: usize) -> &mut Self {
+        if let Err(message) = self.try_to_version(version) {
+            panic!("{message}")
+        }
+        self
+    }
+
+    /// Like [`MigrationTest::to_version`], but returns a description of the failing migration
+    /// instead of panicking. Useful to check that a migration fails with some data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message naming the failing migration and giving the error.
+    pub fn try_to_version(&mut self, version: usize) -> Result<(), String> {
+        let last = self.migrations.ms.len();
+        if version > last {
+            return Err(format!(
+                "cannot migrate to version {version}, the last version is {last}"
+            ));
+        }
+        let mut current = self.version();
+        while current != version {
+            let (next, index, direction) = if current < version {
+                (current + 1, current, "up")
+            } else {
+                (current - 1, current - 1, "down")
+            };
+            self.migrations
+                .to_version(&mut self.conn, next)
+                .map_err(|e| {
+                    format!(
+                        "{} failed when migrating {direction} from version {current} to {next}: {e}",
+                        self.describe_migration(index)
+                    )
+                })?;
+            current = next;
+        }
+        Ok(())
+    }
+
+    /// Apply the next migration
+    ///
+    /// # Panics
+    ///
+    /// Panics if the migration fails.
+    #[track_caller]
+    pub fn up(&mut self) -> &mut Self {
+        let version = self.version() + 1;
+        self.to_version(version)
+    }
+
+    /// Revert the last applied migration
+    ///
+    /// # Panics
+    ///
+    /// Panics if the migration fails or if no migration was applied.
+    #[track_caller]
+    pub fn down(&mut self) -> &mut Self {
+        let Some(version) = self.version().checked_sub(1) else {
+            panic!("cannot migrate down from version 0")
+        };
+        self.to_version(version)
+    }
+
+    /// Execute a statement, to seed data for instance
+    ///
+    /// # Panics
+    ///
+    /// Panics if the statement fails, with a message giving the current version.
+    #[track_caller]
+    pub fn execute(&self, sql: &str, params: impl Params) -> &Self {
+        if let Err(e) = self.conn.execute(sql, params) {
+            panic!("cannot execute “{sql}” {}: {e}", self.describe_version())
+        }
+        self
+    }
+
+    /// Execute several statements, separated by semicolons
+    ///
+    /// # Panics
+    ///
+    /// Panics if a statement fails, with a message giving the current version.
+    #[track_caller]
+    pub fn execute_batch(&self, sql: &str) -> &Self {
+        if let Err(e) = self.conn.execute_batch(sql) {
+            panic!("cannot execute “{sql}” {}: {e}", self.describe_version())
+        }
+        self
+    }
+
+    /// Insert a row in `table`, given as pairs of column names and values
+    ///
+    /// # Panics
+    ///
+    /// Panics if the insertion fails, with a message giving the current version.
+    #[track_caller]
+    pub fn insert(&self, table: &str, row: &[(&str, &dyn ToSql)]) -> &Self {
+        let columns: Vec<String> = row.iter().map(|(c, _)| quote_identifier(c)).collect();
+        let placeholders: Vec<String> = (1..=row.len()).map(|i| format!("?{i}")).collect();
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote_identifier(table),
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+        let values: Vec<&dyn ToSql> = row.iter().map(|(_, v)| *v).collect();
+        self.execute(&sql, values.as_slice())
+    }
+
+    /// Run a query, mapping each row with `f`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query fails, with a message giving the current version.
+    #[track_caller]
+    pub fn query<T>(
+        &self,
+        sql: &str,
+        params: impl Params,
+        f: impl FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    ) -> Vec<T> {
+        let rows = (self.conn.prepare(sql))
+            .and_then(|mut stmt| stmt.query_map(params, f)?.collect::<rusqlite::Result<_>>());
+        match rows {
+            Ok(rows) => rows,
+            Err(e) => panic!("cannot query “{sql}” {}: {e}", self.describe_version()),
+        }
+    }
+
+    /// Run a query, returning the values of the first column
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query fails, with a message giving the current version.
+    #[track_caller]
+    pub fn query_values<T: FromSql>(&self, sql: &str, params: impl Params) -> Vec<T> {
+        self.query(sql, params, |row| row.get(0))
+    }
+
+    /// The underlying connection
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// The underlying connection, to migrate it without this helper for instance
+    pub fn conn_mut(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+
+    /// Name a migration by its index and comment, if any
+    fn describe_migration(&self, index: usize) -> String {
+        let comment = if self.migrations.is_graph() {
+            // The order of the migrations of a graph depends on the database
+            (self.migrations.linearized(&self.conn).ok())
+                .and_then(|linear| Some(linear.ms.get(index)?.comment.as_deref()?.to_owned()))
+        } else {
+            (self.migrations.ms.get(index))
+                .and_then(|m| m.comment.as_deref())
+                .map(str::to_owned)
+        };
+        match comment {
+            Some(comment) => format!("migration {index} (“{comment}”)"),
+            None => format!("migration {index}"),
+        }
+    }
+
+    fn describe_version(&self) -> String {
+        match self.version() {
+            0 => String::from("at version 0"),
+            v => format!("at version {v}, after {}", self.describe_migration(v - 1)),
+        }
+    }
+}
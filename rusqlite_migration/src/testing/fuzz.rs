@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Random walks across the versions of a migration set
+
+use std::fmt;
+
+use log::debug;
+use rusqlite::{types::Value, Connection, ToSql};
+
+use crate::{sql::quote_identifier, user_version_in, Migrations, Schema};
+
+/// Migrate databases along random paths of [`Migrations::to_version`] calls, seeding random
+/// data along the way, and check that:
+/// - a successful migration leads to the schema of the target version, as given by
+///   [`Migrations::schema_snapshots`], with the matching `user_version`. In particular, reversible
+///   paths round-trip the schema,
+/// - a failed migration leaves both the schema and the `user_version` unchanged.
+///
+/// Migrations failing because of the random data are not errors in themselves, as long as
+/// the database is left untouched. Walks are reproducible: they only depend on the seed.
+///
+/// # Example
+///
+/// ```
+/// use rusqlite_migration::{testing::Fuzzer, Migrations, M};
+///
+/// let migrations = Migrations::new(vec![
+///     M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+///     M::up("CREATE UNIQUE INDEX animals_name ON animals(name);")
+///         .down("DROP INDEX animals_name;"),
+/// ]);
+///
+/// Fuzzer::new(&migrations).seed(42).walks(50).run().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct Fuzzer<'a, 'm> {
+    migrations: &'a Migrations<'m>,
+    seed: u64,
+    walks: usize,
+    steps: usize,
+}
+
+/// One step of a walk: optionally seed random data, then migrate to `target`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FuzzStep {
+    /// Version passed to [`Migrations::to_version`]
+    pub target: usize,
+    /// Seed of the random data inserted before migrating, if any
+    pub data_seed: Option<u64>,
+}
+
+/// Minimal path found by [`Fuzzer::run`] leaving the database in an inconsistent state
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct FuzzFailure {
+    /// Seed of the walk that failed
+    pub seed: u64,
+    /// Steps leading to the failure, from an empty database. Steps not needed for the failure
+    /// to occur were removed.
+    pub path: Vec<FuzzStep>,
+    /// Description of the inconsistency
+    pub message: String,
+}
+
+impl<'a, 'm> Fuzzer<'a, 'm> {
+    /// Fuzzer with the default settings: seed 0, 100 walks of 10 steps
+    pub fn new(migrations: &'a Migrations<'m>) -> Self {
+        Self {
+            migrations,
+            seed: 0,
+            walks: 100,
+            steps: 10,
+        }
+    }
+
+    /// Seed of the random generator. Change it to explore other paths.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Number of walks, each one starting from an empty database
+    pub fn walks(mut self, walks: usize) -> Self {
+        self.walks = walks;
+        self
+    }
+
+    /// Number of steps in each walk
+    pub fn steps(mut self, steps: usize) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Run the walks, stopping at the first failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns the shortest failing path found, once reduced. Failing to compute the expected
+    /// schemas is reported with an empty path.
+    pub fn run(&self) -> Result<(), FuzzFailure> {
+        let snapshots = self.snapshots().map_err(|message| FuzzFailure {
+            seed: self.seed,
+            path: Vec::new(),
+            message,
+        })?;
+        let mut rng = SplitMix64(self.seed);
+        let last = self.migrations.ms.len() as u64;
+        for walk in 0..self.walks {
+            let path: Vec<FuzzStep> = (0..self.steps)
+                .map(|_| FuzzStep {
+                    target: rng.below(last + 1) as usize,
+                    data_seed: (rng.below(2) == 0).then(|| rng.next()),
+                })
+                .collect();
+            debug!("walk {walk}: {path:?}");
+
+            if let Err(message) = self.walk(&snapshots, &path) {
+                let (path, message) = self.shrink(&snapshots, path, message);
+                return Err(FuzzFailure {
+                    seed: self.seed,
+                    path,
+                    message,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a single path, from an empty database, to reproduce a failure for instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first inconsistency found.
+    pub fn replay(&self, path: &[FuzzStep]) -> Result<(), String> {
+        self.walk(&self.snapshots()?, path)
+    }
+
+    fn snapshots(&self) -> Result<Vec<Schema>, String> {
+        let snapshots = (self.migrations.schema_snapshots())
+            .map_err(|e| format!("cannot compute the schema of each version: {e}"))?;
+        Ok(snapshots.into_iter().map(|(_, schema)| schema).collect())
+    }
+
+    fn walk(&self, snapshots: &[Schema], path: &[FuzzStep]) -> Result<(), String> {
+        let schema_name = self.migrations.schema.as_deref();
+        let mut conn =
+            (self.migrations.open_scratch()).map_err(|e| format!("cannot open a database: {e}"))?;
+
+        for (i, step) in path.iter().enumerate() {
+            if let Some(data_seed) = step.data_seed {
+                seed_data(&conn, schema_name, data_seed)?;
+            }
+
+            let (version, schema) = state(&conn, schema_name)?;
+            let result = self.migrations.to_version(&mut conn, step.target);
+            let (new_version, new_schema) = state(&conn, schema_name)?;
+            let (expected_version, expected_schema) = match &result {
+                Ok(()) => (step.target, &snapshots[step.target]),
+                Err(_) => (version, &schema),
+            };
+            let outcome = match &result {
+                Ok(()) => String::from("succeeded"),
+                Err(e) => format!("failed with “{e}”"),
+            };
+
+            if new_version != expected_version {
+                return Err(format!(
+                    "step {i}: migrating from version {version} to {} {outcome}, but user_version is {new_version} instead of {expected_version}",
+                    step.target
+                ));
+            }
+            let diff = expected_schema.diff(&new_schema);
+            if !diff.is_empty() {
+                return Err(format!(
+                    "step {i}: migrating from version {version} to {} {outcome}, but the schema differs from the one of version {expected_version}:\n{diff}",
+                    step.target
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove steps and data seeding for as long as the path keeps failing
+    fn shrink(
+        &self,
+        snapshots: &[Schema],
+        mut path: Vec<FuzzStep>,
+        mut message: String,
+    ) -> (Vec<FuzzStep>, String) {
+        loop {
+            let candidates = (0..path.len())
+                .map(|i| {
+                    let mut candidate = path.clone();
+                    candidate.remove(i);
+                    candidate
+                })
+                .chain((0..path.len()).filter_map(|i| {
+                    let mut candidate = path.clone();
+                    candidate[i].data_seed.take().map(|_| candidate)
+                }));
+
+            let mut shrunk = false;
+            for candidate in candidates {
+                if let Err(m) = self.walk(snapshots, &candidate) {
+                    (path, message) = (candidate, m);
+                    shrunk = true;
+                    break;
+                }
+            }
+            if !shrunk {
+                return (path, message);
+            }
+        }
+    }
+}
+
+/// Version and schema of the database attached as `schema`, `main` by default
+fn state(conn: &Connection, schema: Option<&str>) -> Result<(usize, Schema), String> {
+    let version =
+        user_version_in(conn, schema).map_err(|e| format!("cannot read user_version: {e}"))?;
+    let schema =
+        Schema::read_in(conn, schema).map_err(|e| format!("cannot read the schema: {e}"))?;
+    Ok((version, schema))
+}
+
+/// Insert a few random rows in every table of the database attached as `schema`. Insertions
+/// violating constraints are ignored.
+fn seed_data(conn: &Connection, schema: Option<&str>, seed: u64) -> Result<(), String> {
+    let mut rng = SplitMix64(seed);
+    let prefix = schema.map_or_else(String::new, |s| format!("{}.", quote_identifier(s)));
+    let tables = (Schema::read_in(conn, schema))
+        .map_err(|e| format!("cannot read the schema: {e}"))?
+        .tables;
+    for table in &tables {
+        if table.columns.is_empty() {
+            continue;
+        }
+        let columns: Vec<String> = (table.columns.iter())
+            .map(|c| quote_identifier(&c.name))
+            .collect();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{i}")).collect();
+        let sql = format!(
+            "INSERT INTO {prefix}{} ({}) VALUES ({})",
+            quote_identifier(&table.name),
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+        for _ in 0..rng.below(4) {
+            let values: Vec<Value> = (0..columns.len()).map(|_| rng.value()).collect();
+            let params: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+            if let Err(e) = conn.execute(&sql, params.as_slice()) {
+                debug!("ignoring failed insertion in {}: {e}", table.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Small and deterministic random number generator, see
+/// <https://prng.di.unimi.it/splitmix64.c>
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Value of a random type, with a small range so that collisions happen
+    fn value(&mut self) -> Value {
+        match self.below(4) {
+            0 => Value::Null,
+            1 => Value::Integer(self.below(10) as i64),
+            2 => Value::Real(self.below(10) as f64 / 2.0),
+            _ => Value::Text(format!("t{}", self.below(10))),
+        }
+    }
+}
+
+impl fmt::Display for FuzzStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(seed) = self.data_seed {
+            write!(f, "seed data ({seed}), ")?;
+        }
+        write!(f, "to_version({})", self.target)
+    }
+}
+
+impl fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "migrations fuzzing failed with seed {}, path:",
+            self.seed
+        )?;
+        for (i, step) in self.path.iter().enumerate() {
+            writeln!(f, "  {i}: {step}")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FuzzFailure {}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rusqlite::Connection;
+
+use crate::{user_version_in, Error, MigrationDirection, Migrations, M};
+
+fn attached_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "ATTACH DATABASE ':memory:' AS cache; ATTACH DATABASE ':memory:' AS \"user data\";",
+    )
+    .unwrap();
+    conn
+}
+
+fn cache_migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE cache.items (key TEXT PRIMARY KEY);").down("DROP TABLE cache.items;"),
+        M::up("CREATE TABLE cache.values_ (key TEXT REFERENCES items(key), value);")
+            .down("DROP TABLE cache.values_;")
+            .foreign_key_check()
+            .read_compatible_since(1),
+    ])
+    .schema("cache")
+    .store_down_migrations()
+}
+
+fn table_names(conn: &Connection, schema: &str) -> Vec<String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT name FROM \"{schema}\".sqlite_master WHERE type = 'table' ORDER BY name"
+        ))
+        .unwrap();
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn attached_schema_test() {
+    let mut conn = attached_conn();
+    let migrations = cache_migrations();
+
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(user_version_in(&conn, Some("cache")), Ok(2));
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+    assert_eq!(
+        table_names(&conn, "cache"),
+        vec![
+            "items",
+            "rusqlite_migration_compat",
+            "rusqlite_migration_down",
+            "values_"
+        ]
+    );
+    assert!(table_names(&conn, "main").is_empty());
+
+    migrations.to_version(&mut conn, 0).unwrap();
+    assert_eq!(user_version_in(&conn, Some("cache")), Ok(0));
+    assert_eq!(table_names(&conn, "cache"), vec!["rusqlite_migration_down"]);
+}
+
+#[test]
+fn attached_schema_fk_check_test() {
+    let mut conn = attached_conn();
+    let migrations = cache_migrations();
+    migrations.to_version(&mut conn, 1).unwrap();
+    // A violation in the main schema is not reported
+    conn.execute_batch(
+        "PRAGMA foreign_keys = OFF;
+        CREATE TABLE main.parent (id INTEGER PRIMARY KEY);
+        CREATE TABLE main.child (parent INTEGER REFERENCES parent(id));
+        INSERT INTO main.child VALUES (1);",
+    )
+    .unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+
+    migrations.to_version(&mut conn, 1).unwrap();
+    let fails = Migrations::new(vec![
+        cache_migrations().ms[0].clone(),
+        M::up(
+            "CREATE TABLE cache.values_ (key TEXT REFERENCES items(key), value);
+            INSERT INTO cache.values_ VALUES ('missing', 1);",
+        )
+        .foreign_key_check(),
+    ])
+    .schema("cache");
+    assert!(matches!(
+        fails.to_latest(&mut conn),
+        Err(Error::Migration { migration_index: 1, ref err, .. })
+            if matches!(**err, Error::ForeignKeyCheck(_))
+    ));
+}
+
+#[test]
+fn to_latest_all_test() {
+    let mut conn = attached_conn();
+    let main = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    let user_data = Migrations::new(vec![
+        M::up("CREATE TABLE \"user data\".settings (key TEXT);"),
+        M::up("CREATE INDEX \"user data\".settings_key ON settings(key);"),
+    ])
+    .schema("user data");
+
+    let failing = Migrations::new(vec![
+        M::up("CREATE TABLE cache.items (key TEXT PRIMARY KEY);"),
+        M::up("INVALID"),
+    ])
+    .schema("cache");
+    assert!(matches!(
+        Migrations::to_latest_all(&mut conn, &[&main, &user_data, &failing]),
+        Err(Error::Migration {
+            migration_index: 1,
+            direction: MigrationDirection::Up,
+            ..
+        })
+    ));
+    // Nothing was migrated
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+    assert_eq!(user_version_in(&conn, Some("user data")), Ok(0));
+    assert!(table_names(&conn, "main").is_empty());
+    assert!(table_names(&conn, "user data").is_empty());
+
+    let cache = cache_migrations();
+    Migrations::to_latest_all(&mut conn, &[&main, &user_data, &cache]).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+    assert_eq!(user_version_in(&conn, Some("user data")), Ok(2));
+    assert_eq!(user_version_in(&conn, Some("cache")), Ok(2));
+    // Already up to date
+    Migrations::to_latest_all(&mut conn, &[&main, &user_data, &cache]).unwrap();
+
+    // Unknown migrations are reverted with the stored down migrations
+    let older_cache = Migrations::from_iter(cache.ms[..1].iter().cloned())
+        .schema("cache")
+        .store_down_migrations();
+    Migrations::to_latest_all(&mut conn, &[&older_cache]).unwrap();
+    assert_eq!(user_version_in(&conn, Some("cache")), Ok(1));
+}
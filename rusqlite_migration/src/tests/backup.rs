@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::{fs, path::PathBuf};
+
+use rusqlite::Connection;
+
+use crate::{Backup, Error, Migrations, M};
+
+use super::helpers::{m_invalid1, m_valid10_up, m_valid11_up, m_valid20_up};
+
+/// Empty directory, specific to a test
+fn backup_dir(test: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("rusqlite_migration_{test}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn backups(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn backup_keep_last_test() {
+    let dir = backup_dir("backup_keep_last_test");
+    let mut conn = Connection::open_in_memory().unwrap();
+    let mut ms = vec![];
+    for m in [m_valid10_up(), m_valid11_up(), m_valid20_up()] {
+        ms.push(m);
+        let migrations = Migrations::new(ms.clone()).backup(Backup::new(&dir).keep_last(2));
+        migrations.to_latest(&mut conn).unwrap();
+        // Already up to date, no backup is made
+        migrations.to_latest(&mut conn).unwrap();
+    }
+
+    let names = backups(&dir);
+    assert_eq!(names.len(), 2, "{names:?}");
+    assert!(names[0].starts_with("backup-") && names[0].ends_with("-v1.sqlite3"));
+    assert!(names[1].ends_with("-v2.sqlite3"));
+
+    let migrations = Migrations::new(ms).backup(Backup::new(&dir).keep_last(0));
+    migrations.to_version(&mut conn, 0).unwrap_err();
+    assert_eq!(backups(&dir).len(), 3);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backup_restore_test() {
+    let dir = backup_dir("backup_restore_test");
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE animals (name TEXT); INSERT INTO animals VALUES ('dog');")
+        .unwrap();
+
+    let migrations = Migrations::new(vec![M::up("DELETE FROM animals;"), m_invalid1()])
+        .backup(Backup::new(&dir).prefix("animals"));
+    let Err(Error::Backup {
+        err,
+        path,
+        restored,
+    }) = migrations.to_latest(&mut conn)
+    else {
+        panic!("the migration should fail")
+    };
+    assert!(matches!(
+        *err,
+        Error::Migration {
+            migration_index: 1,
+            ..
+        }
+    ));
+    assert!(restored);
+    assert!(backups(&dir)[0].starts_with("animals-"));
+    assert_eq!(path.parent().unwrap(), dir);
+
+    let count: i64 = conn
+        .query_row("SELECT count(*) FROM animals", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backup_no_restore_test() {
+    let dir = backup_dir("backup_no_restore_test");
+    let mut conn = Connection::open_in_memory().unwrap();
+    let migrations =
+        Migrations::new(vec![m_valid10_up(), m_invalid1()]).backup(Backup::new(&dir).no_restore());
+
+    let err = migrations.to_latest(&mut conn).unwrap_err();
+    let Error::Backup {
+        path,
+        restored: false,
+        ..
+    } = &err
+    else {
+        panic!("unexpected error {err:?}")
+    };
+    assert!(err.to_string().ends_with(&format!(
+        "(a backup of the database is at {})",
+        path.display()
+    )));
+
+    let backup = Connection::open(path).unwrap();
+    let version: i64 = backup
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, 0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backup_missing_dir_test() {
+    let dir = backup_dir("backup_missing_dir_test").join("missing");
+    let mut conn = Connection::open_in_memory().unwrap();
+    let migrations = Migrations::new(vec![m_valid10_up()]).backup(Backup::new(&dir));
+
+    assert!(matches!(
+        migrations.to_latest(&mut conn),
+        Err(Error::RusqliteError { .. })
+    ));
+    assert_eq!(
+        migrations.current_version(&conn),
+        Ok(crate::SchemaVersion::NoneSet)
+    );
+}
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{
+    collect::collect_registered, register_migration, Error, MigrationDefinitionError, Migrations,
+    RegisteredMigration, M,
+};
+
+// Registered in reverse order, to check that ordinals are what order migrations
+register_migration!(
+    "food",
+    2,
+    M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+);
+register_migration!("core", 1, M::up("CREATE TABLE animals (name TEXT);"));
+
+#[test]
+fn collect_test() {
+    let migrations = Migrations::collect().unwrap();
+    assert_eq!(
+        migrations,
+        Migrations::new(vec![
+            M::up("CREATE TABLE animals (name TEXT);"),
+            M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+        ])
+    );
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    migrations.to_version(&mut conn, 1).unwrap();
+}
+
+#[test]
+fn collect_duplicate_ordinal_test() {
+    let registered = [
+        RegisteredMigration::new("food", 2, M::up("CREATE TABLE food (name TEXT);")),
+        RegisteredMigration::new("core", 1, M::up("CREATE TABLE animals (name TEXT);")),
+        RegisteredMigration::new("plants", 2, M::up("CREATE TABLE plants (name TEXT);")),
+    ];
+    assert_eq!(
+        collect_registered(&registered),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DuplicateOrdinal {
+                ordinal: 2,
+                modules: ["food", "plants"],
+            }
+        ))
+    );
+}
+
+#[test]
+fn collect_missing_ordinal_test() {
+    let registered = [
+        RegisteredMigration::new("core", 1, M::up("CREATE TABLE animals (name TEXT);")),
+        RegisteredMigration::new("food", 3, M::up("CREATE TABLE food (name TEXT);")),
+    ];
+    assert_eq!(
+        collect_registered(&registered),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::MissingOrdinal { ordinal: 2 }
+        ))
+    );
+    assert_eq!(collect_registered(&[]), Ok(Migrations::new(vec![])));
+}
+
+#[test]
+#[should_panic(expected = "migration ordinals start at 1")]
+fn registered_ordinal_zero_test() {
+    let _ = RegisteredMigration::new("core", 0, M::up(""));
+}
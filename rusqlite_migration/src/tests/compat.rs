@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::num::NonZeroUsize;
+
+use rusqlite::Connection;
+
+use crate::{Compatibility, Error, MigrationDefinitionError, Migrations, Schema, SchemaVersion, M};
+
+fn newer_migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;")
+            .down("ALTER TABLE animals DROP COLUMN legs;")
+            .read_compatible_since(1),
+        M::up("CREATE INDEX animals_name ON animals(name);")
+            .down("DROP INDEX animals_name;")
+            .read_compatible_since(1),
+        M::up("ALTER TABLE animals RENAME TO animal;")
+            .down("ALTER TABLE animal RENAME TO animals;"),
+    ])
+}
+
+fn outside(v: usize) -> SchemaVersion {
+    SchemaVersion::Outside(NonZeroUsize::new(v).unwrap())
+}
+
+#[test]
+fn read_compatible_test() {
+    let newer = newer_migrations();
+    let older = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    let mut conn = Connection::open_in_memory().unwrap();
+
+    assert!(matches!(
+        older.compatibility(&conn),
+        Ok(Compatibility::Migratable(SchemaVersion::NoneSet))
+    ));
+
+    newer.to_version(&mut conn, 3).unwrap();
+    assert_eq!(
+        older.compatibility(&conn),
+        Ok(Compatibility::ReadCompatible {
+            version: outside(3),
+            read_compatible_since: 1
+        })
+    );
+    assert_eq!(
+        older.to_latest(&mut conn),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DatabaseTooFarAhead
+        ))
+    );
+
+    // The last migration is not compatible
+    newer.to_latest(&mut conn).unwrap();
+    assert_eq!(
+        older.compatibility(&conn),
+        Ok(Compatibility::Incompatible {
+            version: outside(4),
+            read_compatible_since: 4
+        })
+    );
+
+    // Going down restores the compatibility
+    newer.to_version(&mut conn, 2).unwrap();
+    assert_eq!(
+        older.compatibility(&conn),
+        Ok(Compatibility::ReadCompatible {
+            version: outside(2),
+            read_compatible_since: 1
+        })
+    );
+    newer.to_version(&mut conn, 1).unwrap();
+    assert!(matches!(
+        older.compatibility(&conn),
+        Ok(Compatibility::Migratable(SchemaVersion::Inside(_)))
+    ));
+}
+
+#[test]
+fn compat_table_test() {
+    let migrations = newer_migrations();
+    let mut conn = Connection::open_in_memory().unwrap();
+    let table_exists = |conn: &Connection| -> bool {
+        conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE name = 'rusqlite_migration_compat'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            == 1
+    };
+
+    migrations.to_version(&mut conn, 1).unwrap();
+    assert!(!table_exists(&conn));
+    migrations.to_version(&mut conn, 2).unwrap();
+    assert!(table_exists(&conn));
+    // The table is not part of the schema of the database
+    assert_eq!(
+        Schema::read(&conn).unwrap(),
+        migrations.schema_snapshots().unwrap()[2].1
+    );
+    migrations.to_version(&mut conn, 0).unwrap();
+    assert!(!table_exists(&conn));
+
+    // The table is still removed once no migration declares a level
+    migrations.to_version(&mut conn, 2).unwrap();
+    let undeclared: Migrations = (migrations.ms.iter().cloned())
+        .map(|m| M {
+            read_compatible_since: None,
+            ..m
+        })
+        .collect();
+    undeclared.to_version(&mut conn, 3).unwrap();
+    assert!(!table_exists(&conn));
+}
+
+#[test]
+fn incompatible_without_declaration_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.pragma_update(None, "user_version", 3).unwrap();
+    let migrations = Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]);
+    assert_eq!(
+        migrations.compatibility(&conn),
+        Ok(Compatibility::Incompatible {
+            version: outside(3),
+            read_compatible_since: 3
+        })
+    );
+}
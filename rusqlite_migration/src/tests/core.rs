@@ -20,38 +20,38 @@ use rusqlite::{Connection, OpenFlags, Transaction};
 use crate::tests::helpers::all_valid_down;
 use crate::{
     tests::helpers::{all_valid_up, m_valid0_up, m_valid10_up, m_valid11_up, m_valid_fk_up},
-    user_version, Error, MigrationDefinitionError, Migrations, SchemaVersion, SchemaVersionError,
-    M,
+    user_version_in, Error, MigrationDefinitionError, MigrationDirection, Migrations,
+    SchemaVersion, SchemaVersionError, M,
 };
 
 use super::helpers::{m_invalid0, m_invalid1, m_valid20_up, m_valid21_up, raw_set_user_version};
 
 #[test]
 fn max_migration_test() {
-    use crate::{set_user_version, user_version};
+    use crate::{set_user_version, user_version_in};
 
     let mut conn = Connection::open_in_memory().unwrap();
     let migrations_max = crate::MIGRATIONS_MAX;
-    set_user_version(&conn, migrations_max).unwrap();
+    set_user_version(&conn, None, migrations_max).unwrap();
     assert_eq!(
-        user_version(&conn),
+        user_version_in(&conn, None),
         Ok(migrations_max),
         "Migration max is too high, it’s not the actual limit",
     );
 
     // Unfortunately SQLite fails silently. But the internal set_user_version returns an error.
     assert_eq!(
-        set_user_version(&conn, migrations_max + 1),
+        set_user_version(&conn, None, migrations_max + 1),
         Err(Error::SpecifiedSchemaVersion(SchemaVersionError::TooHigh))
     );
     assert_eq!(
-        user_version(&conn),
+        user_version_in(&conn, None),
         Ok(migrations_max),
         "set_user_version returned an error but user_version was changed",
     );
     raw_set_user_version(&mut conn, migrations_max as isize + 1);
     assert_eq!(
-        user_version(&conn),
+        user_version_in(&conn, None),
         Ok(0),
         "Migration max is too low, it’s not the actual limit",
     );
@@ -62,7 +62,7 @@ fn max_migration_test() {
 fn min_migrations_test() {
     let mut conn = Connection::open_in_memory().unwrap();
 
-    crate::set_user_version(&conn, 0).unwrap();
+    crate::set_user_version(&conn, None, 0).unwrap();
 
     // The rest of the test also ascertain the behavior of SQLite (and rusqlite)
 
@@ -71,7 +71,10 @@ fn min_migrations_test() {
         conn.query_row("PRAGMA user_version", [], |row| row.get(0)),
         Ok(-3),
     );
-    assert_eq!(crate::user_version(&conn), Err(Error::InvalidUserVersion));
+    assert_eq!(
+        crate::user_version_in(&conn, None),
+        Err(Error::InvalidUserVersion)
+    );
 
     // The minimum user version is a i32::MIN
     raw_set_user_version(&mut conn, i32::MIN as isize);
@@ -79,7 +82,10 @@ fn min_migrations_test() {
         conn.query_row("PRAGMA user_version", [], |row| row.get(0)),
         Ok(i32::MIN),
     );
-    assert_eq!(crate::user_version(&conn), Err(Error::InvalidUserVersion));
+    assert_eq!(
+        crate::user_version_in(&conn, None),
+        Err(Error::InvalidUserVersion)
+    );
 
     // Anything lower than i32::MIN is silently replaced by sqlite
     raw_set_user_version(&mut conn, (i32::MIN as isize).checked_sub(1).unwrap());
@@ -187,12 +193,12 @@ fn user_version_convert_test() {
     let mut conn = Connection::open_in_memory().unwrap();
     let migrations = Migrations::new(vec![m_valid10_up()]);
     assert_eq!(Ok(()), migrations.to_latest(&mut conn));
-    assert_eq!(Ok(1), user_version(&conn));
+    assert_eq!(Ok(1), user_version_in(&conn, None));
     assert_eq!(
         Ok(SchemaVersion::Inside(NonZeroUsize::new(1).unwrap())),
         migrations.current_version(&conn)
     );
-    assert_eq!(1usize, migrations.current_version(&conn).unwrap().into());
+    assert_eq!(1, usize::from(migrations.current_version(&conn).unwrap()));
 }
 
 #[test]
@@ -200,10 +206,10 @@ fn user_version_migrate_test() {
     let mut conn = Connection::open_in_memory().unwrap();
     let migrations = Migrations::new(vec![m_valid10_up()]);
 
-    assert_eq!(Ok(0), user_version(&conn));
+    assert_eq!(Ok(0), user_version_in(&conn, None));
 
     assert_eq!(Ok(()), migrations.to_latest(&mut conn));
-    assert_eq!(Ok(1), user_version(&conn));
+    assert_eq!(Ok(1), user_version_in(&conn, None));
     assert_eq!(
         Ok(SchemaVersion::Inside(NonZeroUsize::new(1).unwrap())),
         migrations.current_version(&conn)
@@ -211,7 +217,7 @@ fn user_version_migrate_test() {
 
     let migrations = Migrations::new(vec![m_valid10_up(), m_valid11_up()]);
     assert_eq!(Ok(()), migrations.to_latest(&mut conn));
-    assert_eq!(Ok(2), user_version(&conn));
+    assert_eq!(Ok(2), user_version_in(&conn, None));
     assert_eq!(
         Ok(SchemaVersion::Inside(NonZeroUsize::new(2).unwrap())),
         migrations.current_version(&conn)
@@ -228,10 +234,72 @@ fn migration_partial_eq_test() {
     assert_ne!(m1, m3);
 }
 
+#[test]
+fn owned_migrations_test() {
+    // Static definitions still work in const contexts
+    const M_STATIC: M<'static> = M::up("CREATE TABLE t0(a);")
+        .down("DROP TABLE t0;")
+        .comment("static");
+
+    fn build(n: usize) -> Migrations<'static> {
+        let mut ms = vec![M_STATIC];
+        for i in 1..n {
+            let sql = format!("CREATE TABLE t{i}(a);");
+            ms.push(
+                M::up_owned(sql)
+                    .down_owned(format!("DROP TABLE t{i};"))
+                    .comment_owned(format!("table {i}")),
+            );
+        }
+        Migrations::new(ms)
+    }
+    let migrations = build(3);
+
+    assert_eq!(
+        migrations,
+        Migrations::new(vec![
+            M::up("CREATE TABLE t0(a);").down("DROP TABLE t0;"),
+            M::up("CREATE TABLE t1(a);").down("DROP TABLE t1;"),
+            M::up("CREATE TABLE t2(a);").down("DROP TABLE t2;"),
+        ])
+    );
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(Ok(3), user_version_in(&conn, None));
+    migrations.to_version(&mut conn, 0).unwrap();
+    assert_eq!(Ok(0), user_version_in(&conn, None));
+
+    // Replacing fields
+    let m = M::up_owned(String::from("SELECT 1;"))
+        .down("SELECT 2;")
+        .down_owned(String::from("SELECT 3;"))
+        .down_owned(String::from("SELECT 4;"))
+        .comment("borrowed")
+        .comment("replaced")
+        .comment_owned(String::from("owned"));
+    assert_eq!(m, M::up("SELECT 1;").down("SELECT 4;").comment("owned"));
+}
+
+#[test]
+fn borrowed_over_owned_test() {
+    // Const methods replace the text set by the owned ones, and conversely
+    let m = M::up_owned(String::from("SELECT 1;"))
+        .down_owned(String::from("SELECT 2;"))
+        .down("SELECT 3;")
+        .comment_owned(String::from("owned"))
+        .comment("borrowed");
+    assert_eq!(m.down.as_deref(), Some("SELECT 3;"));
+    assert_eq!(m.comment.as_deref(), Some("borrowed"));
+
+    let m = m.comment_owned(String::from("owned again"));
+    assert_eq!(m.comment.as_deref(), Some("owned again"));
+}
+
 #[test]
 fn user_version_start_0_test() {
     let conn = Connection::open_in_memory().unwrap();
-    assert_eq!(Ok(0), user_version(&conn))
+    assert_eq!(Ok(0), user_version_in(&conn, None))
 }
 
 #[test]
@@ -247,7 +315,8 @@ fn invalid_migration_multiple_statement_test() {
     let migrations = Migrations::new(vec![m_valid0_up(), m_invalid1()]);
     assert!(matches!(
         dbg!(migrations.validate()),
-        Err(Error::RusqliteError { query: _, err: _ })
+        Err(Error::Migration { migration_index: 1, direction: MigrationDirection::Up, target_version: 2, ref err, .. })
+            if matches!(**err, Error::Statement { .. })
     ));
 }
 
@@ -446,7 +515,7 @@ fn test_from_iter() {
 fn test_user_version_error() {
     // This will cause error because the DB is read only
     let conn = Connection::open_in_memory_with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
-    let e = crate::set_user_version(&conn, 1);
+    let e = crate::set_user_version(&conn, None, 1);
 
     assert!(e.is_err(), "{:?}", e);
     insta::assert_debug_snapshot!(e)
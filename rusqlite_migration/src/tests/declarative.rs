@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rusqlite::Connection;
+
+use crate::{Error, MigrationPlan, Migrations, Schema, SchemaDiff};
+
+const INITIAL: &str = r#"
+    CREATE TABLE animal(name TEXT, legs INTEGER);
+    CREATE TABLE food(name TEXT);
+    CREATE INDEX animal_name ON animal(name);
+    CREATE VIEW animal_names AS SELECT name FROM animal;
+    CREATE TRIGGER animal_insert AFTER INSERT ON animal BEGIN
+        INSERT INTO food(name) VALUES ('hay');
+    END;
+    INSERT INTO animal(name, legs) VALUES ('dog', 4), ('bird', 2);
+"#;
+
+fn initial_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(INITIAL).unwrap();
+    conn
+}
+
+fn assert_plan_reaches(conn: &mut Connection, target: &str) -> MigrationPlan {
+    let plan = MigrationPlan::new(conn, target).unwrap();
+    plan.apply(conn).unwrap();
+
+    let expected = Connection::open_in_memory().unwrap();
+    expected.execute_batch(target).unwrap();
+    assert_eq!(
+        SchemaDiff::between(conn, &expected).unwrap(),
+        SchemaDiff::default()
+    );
+    // Applying the same target again is a no-op
+    assert!(MigrationPlan::new(conn, target).unwrap().is_empty());
+
+    plan
+}
+
+#[test]
+fn empty_plan_test() {
+    let conn = initial_conn();
+    let plan = MigrationPlan::new(&conn, INITIAL).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.sql(), "");
+}
+
+#[test]
+fn add_column_test() {
+    let mut conn = initial_conn();
+    let plan = assert_plan_reaches(
+        &mut conn,
+        r#"
+        CREATE TABLE animal(name TEXT, legs INTEGER, wings INTEGER DEFAULT 0);
+        CREATE TABLE food(name TEXT);
+        CREATE INDEX animal_name ON animal(name);
+        CREATE VIEW animal_names AS SELECT name FROM animal;
+        CREATE TRIGGER animal_insert AFTER INSERT ON animal BEGIN
+            INSERT INTO food(name) VALUES ('hay');
+        END;
+    "#,
+    );
+    assert_eq!(
+        plan.statements(),
+        [r#"ALTER TABLE "animal" ADD COLUMN wings INTEGER DEFAULT 0"#]
+    );
+}
+
+#[test]
+fn rebuild_table_test() {
+    let mut conn = initial_conn();
+    let plan = assert_plan_reaches(
+        &mut conn,
+        r#"
+        CREATE TABLE animal(name TEXT NOT NULL PRIMARY KEY, legs INTEGER CHECK (legs >= 0));
+        CREATE TABLE food(name TEXT, origin TEXT);
+        CREATE INDEX animal_legs ON animal(legs);
+        CREATE VIEW animal_names AS SELECT name FROM animal;
+        CREATE VIEW animal_legs_view AS SELECT name, legs FROM animal;
+        CREATE TRIGGER animal_insert AFTER INSERT ON animal BEGIN
+            INSERT INTO food(name) VALUES ('hay');
+        END;
+    "#,
+    );
+    insta::assert_snapshot!(plan);
+
+    // Data was kept
+    let legs: i64 = conn
+        .query_row("SELECT legs FROM animal WHERE name = 'bird'", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(legs, 2);
+}
+
+#[test]
+fn create_and_drop_test() {
+    let mut conn = initial_conn();
+    let plan = assert_plan_reaches(
+        &mut conn,
+        r#"
+        CREATE TABLE animal(name TEXT, legs INTEGER);
+        CREATE TABLE plant(name TEXT);
+        CREATE INDEX plant_name ON plant(name);
+    "#,
+    );
+    insta::assert_snapshot!(plan);
+}
+
+#[test]
+fn plan_to_m_test() {
+    let conn = initial_conn();
+    let plan = MigrationPlan::new(
+        &conn,
+        "CREATE TABLE animal(name TEXT, legs INTEGER); CREATE TABLE food(name TEXT);",
+    )
+    .unwrap();
+
+    let migrations = Migrations::new(vec![crate::M::up(INITIAL), plan.to_m()]);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(Schema::read(&conn).unwrap().views, vec![]);
+}
+
+#[test]
+fn invalid_target_test() {
+    let conn = initial_conn();
+    assert!(matches!(
+        MigrationPlan::new(&conn, "CREATE TABLE"),
+        Err(Error::RusqliteError { query, .. }) if query == "CREATE TABLE"
+    ));
+}
+
+#[test]
+fn unsupported_table_test() {
+    // SQLite takes the ideographic space as a column name, where it is whitespace for the planner
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE t(a TEXT, \u{3000});")
+        .unwrap();
+    assert!(matches!(
+        MigrationPlan::new(&conn, "CREATE TABLE t(a INTEGER, \u{3000});"),
+        Err(Error::TargetSchemaNotReached(_))
+    ));
+}
+
+#[test]
+fn apply_failure_test() {
+    let mut conn = initial_conn();
+    let plan = MigrationPlan::new(
+        &conn,
+        &format!("{INITIAL}CREATE UNIQUE INDEX animal_legs ON animal(legs);"),
+    )
+    .unwrap();
+    // Data added since the plan was made
+    conn.execute_batch("INSERT INTO animal(name, legs) VALUES ('cat', 4);")
+        .unwrap();
+    let err = plan.apply(&mut conn).unwrap_err();
+    assert!(
+        matches!(err, Error::Statement { ref statement, .. } if statement.contains("animal_legs"))
+    );
+}
@@ -28,12 +28,20 @@ fn test_m_display() {
     insta::assert_snapshot!("up_down_fk_alt", format!("{:#}", m_valid_fk_down()));
 
     let everything = M {
-        up: "UP",
+        up: Cow::Borrowed("UP"),
         up_hook: Some(Box::new(|_: &Transaction| Ok(()))),
-        down: Some("DOWN"),
+        down: Text::from(Some("DOWN")),
         down_hook: Some(Box::new(|_: &Transaction| Ok(()))),
         foreign_key_check: true,
-        comment: Some("Comment, likely a filename in practice!"),
+        comment: Text::owned(String::from("Comment, likely a filename in practice!")),
+        id: Text::from(Some("20240101_everything")),
+        dependencies: Dependencies {
+            declared: Some(&["20231231_before"]),
+            collected: Vec::new(),
+        },
+        allowed_lints: &[LintRule::Vacuum],
+        non_destructive: true,
+        read_compatible_since: Some(0),
     };
     insta::assert_snapshot!("everything", everything);
     insta::assert_debug_snapshot!("everything_debug", everything);
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rusqlite::Connection;
+
+use crate::{
+    tests::helpers::all_valid_down, user_version_in, DowngradePolicy, Error, Migrations, M,
+};
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;")
+            .down("ALTER TABLE animals DROP COLUMN legs;"),
+        M::up("CREATE INDEX animals_name ON animals(name);")
+            .down("DROP INDEX animals_name;")
+            .non_destructive(),
+    ])
+}
+
+#[test]
+fn downgrade_allowed_by_default_test() {
+    let migrations = Migrations::new(all_valid_down());
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    migrations.to_version(&mut conn, 0).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+}
+
+#[test]
+fn downgrade_denied_test() {
+    let migrations = migrations().downgrade_policy(DowngradePolicy::Deny);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+
+    assert_eq!(
+        migrations.to_version(&mut conn, 2),
+        Err(Error::DowngradeDenied {
+            migration_index: 2,
+            policy: DowngradePolicy::Deny
+        })
+    );
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+    // Upgrades are still allowed
+    migrations.to_latest(&mut conn).unwrap();
+}
+
+#[test]
+fn downgrade_non_destructive_only_test() {
+    let migrations = migrations().downgrade_policy(DowngradePolicy::NonDestructiveOnly);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+
+    // Migration 1 is destructive, so nothing is reverted, not even migration 2
+    assert_eq!(
+        migrations.to_version(&mut conn, 0),
+        Err(Error::DowngradeDenied {
+            migration_index: 1,
+            policy: DowngradePolicy::NonDestructiveOnly
+        })
+    );
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+
+    migrations.to_version(&mut conn, 2).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+    insta::assert_debug_snapshot!(migrations);
+}
+
+#[test]
+fn downgrade_database_too_far_ahead_test() {
+    let migrations = migrations().downgrade_policy(DowngradePolicy::Deny);
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.pragma_update(None, "user_version", 4).unwrap();
+    assert!(matches!(
+        migrations.to_version(&mut conn, 1),
+        Err(Error::MigrationDefinition(_))
+    ));
+}
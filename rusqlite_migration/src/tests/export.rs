@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::{Connection, Transaction};
+
+use crate::{
+    user_version_in, Error, MigrationDefinitionError, Migrations, Schema, SchemaVersionError, M,
+};
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![])
+        .module(
+            "core",
+            Migrations::new(vec![
+                M::up("CREATE TABLE animals (id INTEGER PRIMARY KEY, name TEXT) -- No semicolon")
+                    .down("DROP TABLE animals;")
+                    .comment("Animals"),
+                M::up("CREATE TABLE food (name TEXT, animal INTEGER REFERENCES animals(id));")
+                    .down("DROP TABLE food;")
+                    .foreign_key_check()
+                    .read_compatible_since(1),
+            ]),
+        )
+        .module(
+            "plants",
+            Migrations::new(vec![M::up(
+                "CREATE TABLE plants (name TEXT);\nINSERT INTO plants VALUES ('it''s');",
+            )
+            .down("DROP TABLE plants;")]),
+        )
+        .store_down_migrations()
+}
+
+/// Schema, version and content of the tables of the library
+fn state(conn: &Connection) -> (Schema, usize, Vec<String>) {
+    let mut tables = Vec::new();
+    for (table, columns) in [
+        ("rusqlite_migration_compat", "*"),
+        // Not the time migrations were applied
+        (
+            "rusqlite_migration_modules",
+            "version, module, module_version, checksum",
+        ),
+        ("rusqlite_migration_down", "*"),
+    ] {
+        let Ok(mut stmt) = conn.prepare(&format!("SELECT {columns} FROM {table} ORDER BY 1"))
+        else {
+            continue;
+        };
+        let columns = stmt.column_count();
+        let rows = stmt
+            .query_map([], |row| {
+                (0..columns)
+                    .map(|i| {
+                        row.get::<_, rusqlite::types::Value>(i)
+                            .map(|v| format!("{v:?}"))
+                    })
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .unwrap();
+        for row in rows {
+            tables.push(format!("{table}: {}", row.unwrap().join(", ")));
+        }
+    }
+    (
+        Schema::read(conn).unwrap(),
+        user_version_in(conn, None).unwrap(),
+        tables,
+    )
+}
+
+#[test]
+fn export_script_test() {
+    let migrations = migrations();
+    insta::assert_snapshot!("export_up", migrations.export_script(0, 3).unwrap());
+    insta::assert_snapshot!("export_down", migrations.export_script(3, 1).unwrap());
+}
+
+#[test]
+fn export_script_same_as_to_version_test() {
+    let migrations = migrations();
+    let mut conn = Connection::open_in_memory().unwrap();
+    let mut exported = Connection::open_in_memory().unwrap();
+
+    for (from, to) in [(0, 3), (3, 1), (1, 2), (2, 0)] {
+        migrations.to_version(&mut conn, to).unwrap();
+        let script = migrations.export_script(from, to).unwrap();
+        exported.execute_batch(&script).unwrap();
+        assert_eq!(state(&conn), state(&exported), "from {from} to {to}");
+    }
+
+    // The exported state is understood by the library
+    migrations.to_latest(&mut exported).unwrap();
+    assert_eq!(user_version_in(&exported, None), Ok(3));
+}
+
+#[test]
+fn export_script_hooks_test() {
+    let migrations = Migrations::new(vec![M::up_with_hook(
+        "CREATE TABLE animals (name TEXT);",
+        |_: &Transaction| Ok(()),
+    )
+    .down_with_hook("DROP TABLE animals;", |_: &Transaction| Ok(()))])
+    .schema("other");
+    insta::assert_snapshot!("export_hooks_up", migrations.export_script(0, 1).unwrap());
+    insta::assert_snapshot!("export_hooks_down", migrations.export_script(1, 0).unwrap());
+}
+
+#[test]
+fn export_script_errors_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+    ]);
+    assert!(matches!(
+        migrations.export_script(0, 3),
+        Err(Error::SpecifiedSchemaVersion(
+            SchemaVersionError::TargetVersionOutOfRange { .. }
+        ))
+    ));
+    assert!(matches!(
+        migrations.export_script(3, 0),
+        Err(Error::SpecifiedSchemaVersion(
+            SchemaVersionError::TargetVersionOutOfRange { .. }
+        ))
+    ));
+    assert_eq!(
+        migrations.export_script(2, 0),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DownNotDefined { migration_index: 0 }
+        ))
+    );
+    assert!(migrations.export_script(2, 1).is_ok());
+}
+
+#[test]
+fn export_script_fk_violation_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (id INTEGER PRIMARY KEY);"),
+        M::up("CREATE TABLE food (animal INTEGER REFERENCES animals(id));").foreign_key_check(),
+    ]);
+    let script = migrations.export_script(1, 2).unwrap();
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_version(&mut conn, 1).unwrap();
+    conn.execute_batch(&script).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+
+    // Violated before the migration, for instance without `PRAGMA foreign_keys = ON`
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE animals (id INTEGER PRIMARY KEY);
+        CREATE TABLE old_food (animal INTEGER REFERENCES animals(id));
+        INSERT INTO old_food VALUES (1);
+        PRAGMA user_version = 1;",
+    )
+    .unwrap();
+    let err = conn.execute_batch(&script).unwrap_err();
+    assert!(err.to_string().contains("CHECK constraint failed"), "{err}");
+    conn.execute_batch("ROLLBACK;").unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+
+    // Only the schema migrated is checked
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "ATTACH DATABASE ':memory:' AS other;
+        CREATE TABLE main.parents (id INTEGER PRIMARY KEY);
+        CREATE TABLE main.children (parent INTEGER REFERENCES parents(id));
+        INSERT INTO main.children VALUES (1);
+        CREATE TABLE other.animals (id INTEGER PRIMARY KEY);
+        PRAGMA other.user_version = 1;",
+    )
+    .unwrap();
+    let script = Migrations::new(vec![
+        M::up("CREATE TABLE other.animals (id INTEGER PRIMARY KEY);"),
+        M::up("CREATE TABLE other.food (animal INTEGER REFERENCES animals(id));")
+            .foreign_key_check(),
+    ])
+    .schema("other")
+    .export_script(1, 2)
+    .unwrap();
+    conn.execute_batch(&script).unwrap();
+    assert_eq!(user_version_in(&conn, Some("other")), Ok(2));
+}
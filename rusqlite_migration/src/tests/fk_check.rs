@@ -17,7 +17,7 @@ use rusqlite::Connection;
 
 use crate::fk_check::FKCheck;
 use crate::tests::helpers::{m_invalid_fk, m_invalid_fk_down, m_valid0_up, m_valid_fk_up};
-use crate::{Error, Migrations};
+use crate::{Error, MigrationDirection, Migrations};
 
 // Make sure the statement results don’t persist
 #[test]
@@ -72,6 +72,7 @@ fn invalid_down_fk_check_test() {
 
     assert!(matches!(
         migrations.to_version(&mut conn, 0),
-        Err(Error::ForeignKeyCheck(_))
+        Err(Error::Migration { direction: MigrationDirection::Down, ref err, .. })
+            if matches!(**err, Error::ForeignKeyCheck(_))
     ));
 }
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{user_version_in, Error, MigrationDefinitionError, Migrations, M};
+
+const ANIMALS: M<'static> = M::up("CREATE TABLE animals (name TEXT);")
+    .down("DROP TABLE animals;")
+    .id("animals");
+const LEGS: M<'static> = M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;")
+    .down("ALTER TABLE animals DROP COLUMN legs;")
+    .id("legs")
+    .depends_on(&["animals"]);
+const FOOD: M<'static> = M::up("CREATE TABLE food (name TEXT);")
+    .down("DROP TABLE food;")
+    .id("food")
+    .depends_on(&["animals"]);
+
+fn applied_ids(migrations: &Migrations, conn: &Connection) -> Vec<String> {
+    (migrations.status(conn).unwrap().migrations.into_iter())
+        .filter(|m| m.applied)
+        .map(|m| m.id.unwrap())
+        .collect()
+}
+
+#[test]
+fn topological_order_test() {
+    // Dependencies are applied first, whatever the order of the set
+    let migrations = Migrations::new(vec![LEGS, FOOD, ANIMALS]);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(applied_ids(&migrations, &conn), ["animals", "legs", "food"]);
+
+    migrations.validate().unwrap();
+    migrations.validate_reversible().unwrap();
+    assert_eq!(migrations.schema_snapshots().unwrap().len(), 4);
+    assert!(
+        migrations
+            .export_script(0, 3)
+            .unwrap()
+            .find("CREATE TABLE animals")
+            < migrations.export_script(0, 3).unwrap().find("ALTER TABLE")
+    );
+}
+
+#[test]
+fn merged_branch_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![ANIMALS, LEGS])
+        .to_latest(&mut conn)
+        .unwrap();
+
+    // The food migration, from another branch, comes before the legs one in the topological
+    // order, but is applied after it
+    let merged = Migrations::new(vec![ANIMALS, FOOD, LEGS]);
+    merged.verify_applied(&conn).unwrap();
+    merged.to_latest(&mut conn).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+    assert_eq!(applied_ids(&merged, &conn), ["animals", "legs", "food"]);
+    merged.verify_applied(&conn).unwrap();
+
+    // Versions follow the order migrations were applied to this database
+    merged.to_version(&mut conn, 2).unwrap();
+    assert_eq!(applied_ids(&merged, &conn), ["animals", "legs"]);
+    merged.to_id(&mut conn, "animals").unwrap();
+    assert_eq!(applied_ids(&merged, &conn), ["animals"]);
+    merged.to_id(&mut conn, "food").unwrap();
+    assert_eq!(applied_ids(&merged, &conn), ["animals", "food"]);
+    merged.to_latest(&mut conn).unwrap();
+    assert_eq!(applied_ids(&merged, &conn), ["animals", "food", "legs"]);
+}
+
+#[test]
+fn from_linear_test() {
+    // Migrations applied when the set was linear, without ids, keep their position
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("CREATE TABLE food (name TEXT);"),
+    ])
+    .to_latest(&mut conn)
+    .unwrap();
+
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);").id("animals"),
+        M::up("CREATE TABLE food (name TEXT);").id("food"),
+        LEGS,
+    ]);
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(applied_ids(&migrations, &conn), ["animals", "food", "legs"]);
+}
+
+#[test]
+fn from_linear_reordered_test() {
+    // Only the second migration had an id when the set was linear
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("CREATE TABLE food (name TEXT);").id("food"),
+    ])
+    .to_latest(&mut conn)
+    .unwrap();
+
+    // The first version is assumed to be the first migration, which is the food one now
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE food (name TEXT);").id("food"),
+        M::up("CREATE TABLE animals (name TEXT);").id("animals"),
+        LEGS,
+    ]);
+    let err = migrations.to_latest(&mut conn).unwrap_err();
+    insta::assert_snapshot!(err, @"rusqlite_migration error: a migration without id was applied at version 1, but migration “food”, also applied at version 2, is now defined at that version. Migrations must only be appended.");
+    assert!(matches!(err, Error::MigrationsReordered { version: 1, .. }));
+    assert_eq!(migrations.status(&conn).err(), Some(err));
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+}
+
+#[test]
+fn deleted_migration_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![ANIMALS, LEGS])
+        .to_latest(&mut conn)
+        .unwrap();
+
+    let migrations = Migrations::new(vec![ANIMALS, FOOD]);
+    assert_eq!(
+        migrations.to_latest(&mut conn),
+        Err(Error::UnknownMigrationId(String::from("legs")))
+    );
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+}
+
+#[test]
+fn invalid_graph_test() {
+    let definition_error = |e| Err(Error::MigrationDefinition(e));
+
+    assert_eq!(
+        Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);"), LEGS]).validate(),
+        definition_error(MigrationDefinitionError::MissingId { migration_index: 0 })
+    );
+    assert_eq!(
+        Migrations::new(vec![LEGS]).validate(),
+        definition_error(MigrationDefinitionError::UnknownDependency { migration_index: 0 })
+    );
+    assert_eq!(
+        Migrations::new(vec![
+            ANIMALS,
+            M::up("SELECT 1;").id("a").depends_on(&["b"]),
+            M::up("SELECT 2;").id("b").depends_on(&["a"]),
+        ])
+        .validate(),
+        definition_error(MigrationDefinitionError::DependencyCycle { migration_index: 1 })
+    );
+    assert_eq!(
+        Migrations::new(vec![ANIMALS, FOOD, FOOD]).validate(),
+        definition_error(MigrationDefinitionError::DuplicateId {
+            migration_indices: [1, 2]
+        })
+    );
+}
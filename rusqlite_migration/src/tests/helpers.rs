@@ -116,7 +116,12 @@ fn all_valid_consistent() {
             down_hook: _,
             foreign_key_check: left_foreign_key_check,
             comment: left_comment,
-        } = all_up[i];
+            id: left_id,
+            dependencies: left_dependencies,
+            allowed_lints: left_allowed_lints,
+            non_destructive: left_non_destructive,
+            read_compatible_since: left_read_compatible_since,
+        } = &all_up[i];
         let M {
             up: right_up,
             up_hook: _,
@@ -124,11 +129,21 @@ fn all_valid_consistent() {
             down_hook: _,
             foreign_key_check: right_foreign_key_check,
             comment: right_comment,
-        } = all_down[i];
+            id: right_id,
+            dependencies: right_dependencies,
+            allowed_lints: right_allowed_lints,
+            non_destructive: right_non_destructive,
+            read_compatible_since: right_read_compatible_since,
+        } = &all_down[i];
 
         assert_eq!(left_up, right_up);
         assert_eq!(left_foreign_key_check, right_foreign_key_check);
         assert_eq!(left_comment, right_comment);
+        assert_eq!(left_id, right_id);
+        assert_eq!(left_dependencies, right_dependencies);
+        assert_eq!(left_allowed_lints, right_allowed_lints);
+        assert_eq!(left_non_destructive, right_non_destructive);
+        assert_eq!(left_read_compatible_since, right_read_compatible_since);
 
         assert!(left_down.is_none());
         assert!(right_down.is_some());
@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{user_version_in, Error, MigrationDefinitionError, Migrations, SchemaVersion, M};
+
+fn migrations() -> Vec<M<'static>> {
+    vec![
+        M::up("CREATE TABLE animals (name TEXT);")
+            .down("DROP TABLE animals;")
+            .id("create_animals"),
+        M::up("CREATE TABLE food (name TEXT);")
+            .down("DROP TABLE food;")
+            .id("create_food"),
+        M::up("CREATE TABLE plants (name TEXT);")
+            .down("DROP TABLE plants;")
+            .id_owned(String::from("create_plants")),
+    ]
+}
+
+fn ids(conn: &Connection) -> Vec<(i64, Option<String>)> {
+    let mut stmt = conn
+        .prepare("SELECT version, id FROM rusqlite_migration_modules ORDER BY version")
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn to_id_test() {
+    let migrations = Migrations::new(migrations());
+    assert_eq!(
+        migrations.version_of("create_plants"),
+        Some(SchemaVersion::Inside(
+            std::num::NonZeroUsize::new(3).unwrap()
+        ))
+    );
+    assert_eq!(migrations.version_of("create_fruits"), None);
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_id(&mut conn, "create_food").unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+    assert_eq!(
+        ids(&conn),
+        vec![
+            (1, Some(String::from("create_animals"))),
+            (2, Some(String::from("create_food"))),
+        ]
+    );
+
+    migrations.to_latest(&mut conn).unwrap();
+    migrations.to_id(&mut conn, "create_animals").unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+    assert_eq!(ids(&conn).len(), 1);
+
+    let err = migrations.to_id(&mut conn, "create_fruits").unwrap_err();
+    assert_eq!(
+        err,
+        Error::UnknownMigrationId(String::from("create_fruits"))
+    );
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+}
+
+#[test]
+fn deleted_migration_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(migrations()).to_latest(&mut conn).unwrap();
+    Migrations::new(migrations()).verify_applied(&conn).unwrap();
+
+    let mut ms = migrations();
+    ms.retain(|m| m.id.as_deref() != Some("create_food"));
+    let migrations = Migrations::new(ms);
+    let err = migrations.verify_applied(&conn).unwrap_err();
+    insta::assert_snapshot!(err, @"rusqlite_migration error: migration 2 outside of any module (id “create_food”, checksum 5b40672f71a94425) was applied at version 2, but migration 2 outside of any module (id “create_plants”, checksum fb9f707a0db6df2b) is now defined at that version. Migrations must only be appended.");
+    assert_eq!(migrations.to_latest(&mut conn), Err(err));
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+}
+
+#[test]
+fn renamed_migration_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(migrations()).to_latest(&mut conn).unwrap();
+
+    let mut ms = migrations();
+    ms[0] = M::up("CREATE TABLE animals (name TEXT);").id("animals");
+    assert!(matches!(
+        Migrations::new(ms).verify_applied(&conn),
+        Err(Error::MigrationsReordered { version: 1, .. })
+    ));
+}
+
+#[test]
+fn id_added_later_test() {
+    // Migrations applied without ids are not recorded, and the ones recorded without ids still
+    // match once given one
+    let mut conn = Connection::open_in_memory().unwrap();
+    let without_ids = || {
+        Migrations::new(vec![]).module(
+            "core",
+            Migrations::new(vec![M::up("CREATE TABLE animals (name TEXT);")]),
+        )
+    };
+    without_ids().to_latest(&mut conn).unwrap();
+    assert_eq!(ids(&conn), vec![(1, None)]);
+
+    let with_ids = Migrations::new(vec![]).module(
+        "core",
+        Migrations::new(vec![
+            M::up("CREATE TABLE animals (name TEXT);").id("create_animals"),
+            M::up("CREATE TABLE food (name TEXT);").id("create_food"),
+        ]),
+    );
+    with_ids.to_latest(&mut conn).unwrap();
+    assert_eq!(
+        ids(&conn),
+        vec![(1, None), (2, Some(String::from("create_food")))]
+    );
+}
+
+#[test]
+fn duplicate_id_test() {
+    let mut ms = migrations();
+    ms[2] = M::up("CREATE TABLE plants (name TEXT);").id("create_animals");
+    let migrations = Migrations::new(ms);
+
+    let expected = Err(Error::MigrationDefinition(
+        MigrationDefinitionError::DuplicateId {
+            migration_indices: [0, 2],
+        },
+    ));
+    assert_eq!(migrations.validate(), expected);
+    let mut conn = Connection::open_in_memory().unwrap();
+    assert_eq!(migrations.to_latest(&mut conn), expected);
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+}
+
+#[test]
+fn borrowed_over_owned_id_test() {
+    let m = M::up("SELECT 1;")
+        .id_owned(String::from("owned"))
+        .id("borrowed");
+    assert_eq!(m.id.as_deref(), Some("borrowed"));
+    assert_eq!(
+        m.id_owned(String::from("owned")).id.as_deref(),
+        Some("owned")
+    );
+}
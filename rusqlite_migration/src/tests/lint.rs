@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{Error, LintLevel, LintRule, MigrationDirection, Migrations, M};
+
+#[test]
+fn lint_rules_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE t(a);\nBEGIN; COMMIT;").down("DROP TABLE t;"),
+        M::up("SAVEPOINT s; ROLLBACK TO s; RELEASE s; ROLLBACK;"),
+        M::up(
+            "PRAGMA main.foreign_keys = OFF; PRAGMA foreign_key_check;\n  pragma JOURNAL_MODE=WAL;",
+        ),
+        M::up("ATTACH 'other.db' AS other; DETACH other; VACUUM;"),
+        M::up("DROP TABLE t; -- DROP TABLE u;").down("CREATE TABLE t(a); DROP TABLE u;"),
+        M::up("DROP TABLE t;"),
+        // Statements in a trigger are not linted
+        M::up("CREATE TRIGGER tr AFTER INSERT ON t BEGIN SELECT RAISE(ROLLBACK, 'no'); END;"),
+    ]);
+
+    let lints: Vec<_> = (migrations.lint().into_iter())
+        .map(|l| {
+            assert_eq!(l.level, LintLevel::Warn);
+            (
+                l.migration_index,
+                l.direction,
+                l.rule,
+                l.statement,
+                l.line,
+                l.column,
+            )
+        })
+        .collect();
+    use LintRule::*;
+    use MigrationDirection::*;
+    assert_eq!(
+        lints,
+        vec![
+            (0, Up, TransactionControl, "BEGIN;".to_owned(), 2, 1),
+            (0, Up, TransactionControl, "COMMIT;".to_owned(), 2, 8),
+            (1, Up, TransactionControl, "ROLLBACK;".to_owned(), 1, 40),
+            (
+                2,
+                Up,
+                ForeignKeysPragma,
+                "PRAGMA main.foreign_keys = OFF;".to_owned(),
+                1,
+                1
+            ),
+            (
+                2,
+                Up,
+                JournalModePragma,
+                "pragma JOURNAL_MODE=WAL;".to_owned(),
+                2,
+                3
+            ),
+            (
+                3,
+                Up,
+                Attach,
+                "ATTACH 'other.db' AS other;".to_owned(),
+                1,
+                1
+            ),
+            (3, Up, Attach, "DETACH other;".to_owned(), 1, 29),
+            (3, Up, Vacuum, "VACUUM;".to_owned(), 1, 43),
+            (
+                5,
+                Up,
+                DropTableWithoutDown,
+                "DROP TABLE t;".to_owned(),
+                1,
+                1
+            ),
+        ]
+    );
+}
+
+#[test]
+fn lint_suppression_test() {
+    let migrations = Migrations::new(vec![
+        M::up("VACUUM; DROP TABLE t;")
+            .allow_lints(&[LintRule::Vacuum, LintRule::DropTableWithoutDown]),
+        M::up("VACUUM; BEGIN;"),
+    ]);
+    let rules: Vec<_> = migrations.lint().into_iter().map(|l| l.rule).collect();
+    assert_eq!(rules, vec![LintRule::Vacuum, LintRule::TransactionControl]);
+
+    let migrations = migrations.lint_level(LintRule::Vacuum, LintLevel::Allow);
+    let rules: Vec<_> = migrations.lint().into_iter().map(|l| l.rule).collect();
+    assert_eq!(rules, vec![LintRule::TransactionControl]);
+}
+
+#[test]
+fn validate_lint_level_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE t(a);"),
+        M::up("PRAGMA foreign_keys = ON;").comment("Enable foreign keys"),
+    ]);
+    // Lints are only warnings by default
+    assert_eq!(migrations.validate(), Ok(()));
+
+    let migrations = migrations
+        .lint_level(LintRule::ForeignKeysPragma, LintLevel::Warn)
+        .lint_level(LintRule::ForeignKeysPragma, LintLevel::Deny);
+    let Err(Error::Lint(lints)) = migrations.validate() else {
+        panic!("the lint should be denied");
+    };
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].level, LintLevel::Deny);
+    insta::assert_snapshot!(Error::Lint(lints));
+    insta::assert_debug_snapshot!(migrations);
+}
@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::{Connection, Transaction};
+
+use crate::{
+    Error, HookRegistry, LintRule, MigrationDirection, MigrationManifest, Migrations,
+    SchemaVersion, M,
+};
+
+const MANIFEST: &str = r#"{
+  "migrations": [
+    {
+      "up": "CREATE TABLE animals (name TEXT);",
+      "down": "DROP TABLE animals;",
+      "comment": "Create animals"
+    },
+    {
+      "up": "CREATE TABLE food (name TEXT);",
+      "foreign_key_check": true,
+      "non_destructive": true
+    },
+    {
+      "up": "DROP TABLE food;",
+      "allowed_lints": [
+        "DropTableWithoutDown"
+      ],
+      "read_compatible_since": 1
+    }
+  ]
+}"#;
+
+#[test]
+fn from_manifest_test() {
+    let manifest: MigrationManifest = serde_json::from_str(MANIFEST).unwrap();
+    let migrations = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap();
+
+    let expected = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);")
+            .down("DROP TABLE animals;")
+            .comment("Create animals"),
+        M::up("CREATE TABLE food (name TEXT);")
+            .foreign_key_check()
+            .non_destructive(),
+        M::up("DROP TABLE food;")
+            .allow_lints(&[LintRule::DropTableWithoutDown])
+            .read_compatible_since(1),
+    ]);
+    assert_eq!(migrations, expected);
+    assert_eq!(Ok(()), migrations.validate());
+}
+
+#[test]
+fn round_trip_test() {
+    let manifest: MigrationManifest = serde_json::from_str(MANIFEST).unwrap();
+    let migrations = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap();
+
+    assert_eq!(migrations.to_manifest().as_ref(), Ok(&manifest));
+    assert_eq!(serde_json::to_string_pretty(&migrations).unwrap(), MANIFEST);
+}
+
+#[test]
+fn repeatable_round_trip_test() {
+    let json = r#"{
+  "migrations": [
+    {
+      "up": "CREATE TABLE animals (name TEXT, legs INTEGER);"
+    }
+  ],
+  "repeatable": [
+    {
+      "name": "bipeds",
+      "sql": "DROP VIEW IF EXISTS bipeds; CREATE VIEW bipeds AS SELECT name FROM animals WHERE legs = 2;"
+    }
+  ]
+}"#;
+    let manifest: MigrationManifest = serde_json::from_str(json).unwrap();
+    let migrations = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap();
+    assert_eq!(
+        migrations,
+        Migrations::new(vec![M::up(
+            "CREATE TABLE animals (name TEXT, legs INTEGER);"
+        )])
+        .repeatable(
+            "bipeds",
+            "DROP VIEW IF EXISTS bipeds; CREATE VIEW bipeds AS SELECT name FROM animals WHERE legs = 2;"
+        )
+    );
+
+    assert_eq!(migrations.to_manifest().as_ref(), Ok(&manifest));
+    assert_eq!(serde_json::to_string_pretty(&migrations).unwrap(), json);
+}
+
+#[test]
+fn unknown_field_test() {
+    let res = serde_json::from_str::<MigrationManifest>(
+        r#"{"migrations": [{"up": "SELECT 1;", "dwon": "SELECT 2;"}]}"#,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn hook_registry_test() {
+    let hooks = HookRegistry::new()
+        .register("seed", |tx: &Transaction| {
+            tx.execute("INSERT INTO animals (name) VALUES ('dog')", [])?;
+            Ok(())
+        })
+        .register("clear", |tx: &Transaction| {
+            tx.execute("DELETE FROM animals", [])?;
+            Ok(())
+        });
+    let manifest: MigrationManifest = serde_json::from_str(
+        r#"{"migrations": [
+            {"up": "CREATE TABLE animals (name TEXT);", "down": "DROP TABLE animals;"},
+            {"up": "", "up_hook": "seed", "down": "", "down_hook": "clear"}
+        ]}"#,
+    )
+    .unwrap();
+    let migrations = Migrations::from_manifest(&manifest, &hooks).unwrap();
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    let count = |conn: &Connection| -> i64 {
+        conn.query_row("SELECT count(*) FROM animals", [], |r| r.get(0))
+            .unwrap()
+    };
+    assert_eq!(count(&conn), 1);
+    migrations.to_version(&mut conn, 1).unwrap();
+    assert_eq!(count(&conn), 0);
+
+    // Hooks have no name to be serialized with
+    let err = migrations.to_manifest().unwrap_err();
+    assert!(matches!(err, Error::Manifest(_)));
+    insta::assert_snapshot!(err, @"rusqlite_migration error in migration manifest: migrations with hooks can’t be serialized, as hooks have no name");
+    let err = serde_json::to_string(&migrations).unwrap_err();
+    insta::assert_snapshot!(err, @"rusqlite_migration error in migration manifest: migrations with hooks can’t be serialized, as hooks have no name");
+}
+
+#[test]
+fn unknown_hook_test() {
+    let manifest: MigrationManifest =
+        serde_json::from_str(r#"{"migrations": [{"up": "", "up_hook": "seed"}]}"#).unwrap();
+    let err = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap_err();
+    assert!(matches!(err, Error::Manifest(_)));
+    insta::assert_snapshot!(err, @"rusqlite_migration error in migration manifest: no hook registered under the name “seed”");
+}
+
+#[test]
+fn serialize_types_test() {
+    assert_eq!(
+        serde_json::to_string(&SchemaVersion::Inside(
+            std::num::NonZeroUsize::new(2).unwrap()
+        ))
+        .unwrap(),
+        r#"{"Inside":2}"#
+    );
+    let err = Error::with_sql(rusqlite::Error::InvalidQuery, "SELECT 1").in_migration(
+        0,
+        MigrationDirection::Up,
+        1,
+        Some("First"),
+    );
+    insta::assert_snapshot!(serde_json::to_string(&err).unwrap(), @r#"{"Migration":{"migration_index":0,"direction":"Up","target_version":1,"comment":"First","err":{"RusqliteError":{"query":"SELECT 1","err":"Query is not read-only"}}}}"#);
+}
+
+#[test]
+fn replace_manifest_fields_test() {
+    let manifest: MigrationManifest = serde_json::from_str(
+        r#"{"migrations": [{"up": "SELECT 1;", "id": "first"}, {"up": "SELECT 2;", "depends_on": ["first"], "comment": "second"}]}"#,
+    )
+    .unwrap();
+    let migrations = Migrations::from_manifest(&manifest, &HookRegistry::new()).unwrap();
+    assert_eq!(&*migrations.ms[1].dependencies, ["first"]);
+
+    let m = (migrations.ms[1].clone())
+        .depends_on(&["other"])
+        .comment("replaced");
+    assert_eq!(&*m.dependencies, ["other"]);
+    assert_eq!(m.comment.as_deref(), Some("replaced"));
+}
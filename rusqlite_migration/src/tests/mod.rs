@@ -13,10 +13,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod attached;
+#[cfg(feature = "backup")]
+mod backup;
 #[cfg(feature = "from-directory")]
 mod builder;
 
+#[cfg(feature = "collect")]
+mod collect;
+mod compat;
 mod core;
+mod declarative;
 mod display;
+mod downgrade;
+mod export;
 mod fk_check;
+mod graph;
 mod helpers;
+mod ids;
+mod lint;
+#[cfg(feature = "serde")]
+mod manifest;
+mod modules;
+mod rebuild;
+#[cfg(feature = "backup")]
+mod rehearsal;
+mod repeatable;
+mod schema;
+mod sql;
+mod status;
+mod stored_down;
+#[cfg(feature = "testing")]
+mod testing;
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{user_version_in, Error, Migrations, SchemaVersion, M};
+
+fn core() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;")
+            .down("ALTER TABLE animals DROP COLUMN legs;"),
+    ])
+}
+
+fn food() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;")
+    ])
+}
+
+fn modules(conn: &Connection) -> Vec<(i64, Option<String>, i64)> {
+    let mut stmt = conn
+        .prepare("SELECT version, module, module_version FROM rusqlite_migration_modules ORDER BY version")
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn append_test() {
+    let migrations = core().append(food());
+    assert_eq!(
+        migrations,
+        Migrations::new(core().ms.iter().chain(food().ms.iter()).cloned().collect())
+    );
+
+    // Without modules, nothing is recorded
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+    assert!(!migrations
+        .table_exists(&conn, "rusqlite_migration_modules")
+        .unwrap());
+}
+
+#[test]
+fn module_test() {
+    let migrations = Migrations::new(vec![M::up("CREATE TABLE config (key TEXT);")])
+        .module("core", core())
+        .module("food", food());
+    let v = |n| {
+        Some(SchemaVersion::Inside(
+            std::num::NonZeroUsize::new(n).unwrap(),
+        ))
+    };
+    assert_eq!(migrations.module_version("core", 0), v(1));
+    assert_eq!(migrations.module_version("core", 2), v(3));
+    assert_eq!(migrations.module_version("food", 1), v(4));
+    assert_eq!(migrations.module_version("food", 2), None);
+    assert_eq!(migrations.module_version("plants", 0), None);
+    assert_eq!(
+        Migrations::new(vec![])
+            .module("core", core())
+            .module_version("core", 0),
+        Some(SchemaVersion::NoneSet)
+    );
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(
+        modules(&conn),
+        vec![
+            (1, None, 1),
+            (2, Some(String::from("core")), 1),
+            (3, Some(String::from("core")), 2),
+            (4, Some(String::from("food")), 1),
+        ]
+    );
+
+    migrations.to_version(&mut conn, 2).unwrap();
+    assert_eq!(modules(&conn).len(), 2);
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(modules(&conn).len(), 4);
+
+    // Appending migrations, or changing comments and whitespace, is fine
+    let appended = Migrations::new(vec![M::up("CREATE TABLE config (key TEXT); -- Settings")])
+        .module("core", core())
+        .module(
+            "food",
+            food().append(Migrations::new(vec![M::up(
+                "CREATE TABLE fruits (name TEXT);",
+            )])),
+        );
+    appended.to_latest(&mut conn).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(5));
+}
+
+#[test]
+fn module_inserted_migration_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![])
+        .module("core", core())
+        .module("food", food())
+        .to_latest(&mut conn)
+        .unwrap();
+
+    // A migration inserted in the middle of the core module
+    let mut ms = core().ms.into_owned();
+    ms.insert(
+        1,
+        M::up("CREATE TABLE plants (name TEXT);").down("DROP TABLE plants;"),
+    );
+    let migrations = Migrations::new(vec![])
+        .module("core", Migrations::new(ms))
+        .module("food", food());
+
+    let err = migrations.to_latest(&mut conn).unwrap_err();
+    assert!(matches!(err, Error::MigrationsReordered { version: 2, .. }));
+    insta::assert_snapshot!(err, @"rusqlite_migration error: migration 2 of module “core” (checksum d07925b4d42aaf89) was applied at version 2, but migration 2 of module “core” (checksum fb9f707a0db6df2b) is now defined at that version. Migrations must only be appended.");
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+
+    // Reverting is guarded too
+    assert!(matches!(
+        migrations.to_version(&mut conn, 0),
+        Err(Error::MigrationsReordered { version: 2, .. })
+    ));
+    assert_eq!(user_version_in(&conn, None), Ok(3));
+
+    // Modules registered in another order
+    let err = Migrations::new(vec![])
+        .module("food", food())
+        .module("core", core())
+        .to_latest(&mut conn)
+        .unwrap_err();
+    assert!(matches!(err, Error::MigrationsReordered { version: 1, .. }));
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::{Connection, Transaction};
+
+use crate::{rebuild_table, Error, ForeignKeyCheckError, HookError, Migrations, Schema, M};
+
+const ANIMALS: M = M::up(
+    r#"
+    CREATE TABLE food(name TEXT PRIMARY KEY);
+    CREATE TABLE animal(name TEXT, legs INTEGER, food TEXT REFERENCES food(name));
+    CREATE INDEX animal_name ON animal(name);
+    CREATE VIEW animal_names AS SELECT name FROM animal;
+    CREATE TRIGGER food_delete AFTER DELETE ON food BEGIN
+        DELETE FROM animal WHERE food = old.name;
+    END;
+    INSERT INTO food(name) VALUES ('meat'), ('seeds');
+    INSERT INTO animal(name, legs, food) VALUES ('dog', 4, 'meat'), ('bird', 2, 'seeds');
+"#,
+);
+
+#[test]
+fn rebuild_table_test() {
+    let migrations = Migrations::new(vec![
+        ANIMALS,
+        M::rebuild_table(
+            "animal",
+            "CREATE TABLE animal(name TEXT NOT NULL, food TEXT REFERENCES food(name), wings INTEGER)",
+        ),
+    ]);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+
+    let rows: Vec<(String, String, Option<i64>)> = conn
+        .prepare("SELECT name, food, wings FROM animal ORDER BY name")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("bird".to_owned(), "seeds".to_owned(), None),
+            ("dog".to_owned(), "meat".to_owned(), None)
+        ]
+    );
+
+    let schema = Schema::read(&conn).unwrap();
+    let animal = schema.tables.iter().find(|t| t.name == "animal").unwrap();
+    assert!(animal.columns[0].not_null);
+    assert_eq!(animal.indexes[0].name, "animal_name");
+    assert_eq!(schema.views[0].name, "animal_names");
+    assert_eq!(schema.triggers[0].name, "food_delete");
+}
+
+#[test]
+fn rebuild_table_invalid_sql_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    let migrations = Migrations::new(vec![
+        ANIMALS,
+        M::rebuild_table("animal", "CREATE VIEW animal AS SELECT 1"),
+    ]);
+    assert!(matches!(
+        migrations.to_latest(&mut conn),
+        Err(Error::Migration { migration_index: 1, err, .. })
+            if matches!(&*err, Error::Hook(msg) if msg.contains("cannot rebuild table animal"))
+    ));
+}
+
+#[test]
+fn rebuild_table_fk_check_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![ANIMALS]).to_latest(&mut conn).unwrap();
+
+    let tx = conn.transaction().unwrap();
+    tx.execute_batch("UPDATE animal SET food = 'fish' WHERE name = 'dog'")
+        .unwrap();
+    let result = rebuild_table(
+        &tx,
+        "food",
+        "CREATE TABLE food(name TEXT PRIMARY KEY, calories INTEGER)",
+    );
+    assert_eq!(
+        result,
+        Err(HookError::ForeignKeyCheck(vec![ForeignKeyCheckError {
+            table: "animal".to_owned(),
+            rowid: 1,
+            parent: "food".to_owned(),
+            fkid: 0
+        }]))
+    );
+}
+
+#[test]
+fn rebuild_table_hook_test() {
+    let migrations = Migrations::new(vec![
+        ANIMALS,
+        M::up_with_hook("DROP INDEX animal_name;", |tx: &Transaction| {
+            rebuild_table(tx, "animal", "CREATE TABLE animal(legs INTEGER, food TEXT)")
+        }),
+    ]);
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+
+    let legs: i64 = conn
+        .query_row("SELECT sum(legs) FROM animal", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(legs, 6);
+}
@@ -0,0 +1,240 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{Error, ForeignKeyCheckError, MigrationDefinitionError, Migrations, M};
+
+use super::helpers::{all_valid_up, m_valid10_up};
+
+fn animals_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE animals (name TEXT); INSERT INTO animals VALUES ('dog'), ('dog');
+        PRAGMA user_version = 1;",
+    )
+    .unwrap();
+    conn
+}
+
+fn user_version(conn: &Connection) -> i64 {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn rehearse_success_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    let migrations = Migrations::new(all_valid_up());
+
+    let rehearsal = migrations.rehearse(&conn).unwrap();
+    assert!(rehearsal.is_success(), "{rehearsal}");
+    assert_eq!(rehearsal.from_version, 0);
+    assert_eq!(
+        (rehearsal.steps.iter())
+            .map(|s| s.migration_index)
+            .collect::<Vec<_>>(),
+        (0..all_valid_up().len()).collect::<Vec<_>>()
+    );
+    assert_eq!(user_version(&conn), 0);
+}
+
+#[test]
+fn rehearse_data_failure_test() {
+    let conn = animals_conn();
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;").comment("Count legs"),
+        M::up("CREATE UNIQUE INDEX animals_name ON animals(name);"),
+    ]);
+
+    let rehearsal = migrations.rehearse(&conn).unwrap();
+    assert_eq!(rehearsal.from_version, 1);
+    assert_eq!(rehearsal.steps.len(), 1);
+    assert_eq!(rehearsal.steps[0].migration_index, 1);
+    assert_eq!(rehearsal.steps[0].comment.as_deref(), Some("Count legs"));
+    assert!(matches!(
+        rehearsal.error,
+        Some(Error::Migration { migration_index: 2, ref err, .. })
+            if matches!(**err, Error::Statement { ref statement, .. } if statement.contains("UNIQUE"))
+    ));
+
+    // The original database is untouched
+    assert_eq!(user_version(&conn), 1);
+    conn.execute("INSERT INTO animals (name) VALUES ('cat')", [])
+        .unwrap();
+    conn.execute("INSERT INTO animals (name, legs) VALUES ('cat', 4)", [])
+        .unwrap_err();
+}
+
+#[test]
+fn rehearse_fk_violations_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    let migrations = Migrations::new(vec![
+        M::up(
+            "CREATE TABLE food (name TEXT PRIMARY KEY);
+            CREATE TABLE animals (name TEXT, food TEXT REFERENCES food(name));
+            INSERT INTO food VALUES ('meat');
+            INSERT INTO animals VALUES ('dog', 'meat');",
+        ),
+        M::up("DELETE FROM food;"),
+    ]);
+
+    let rehearsal = migrations.rehearse(&conn).unwrap();
+    assert_eq!(rehearsal.error, None);
+    assert_eq!(rehearsal.steps.len(), 2);
+    assert_eq!(
+        rehearsal.foreign_key_violations,
+        vec![ForeignKeyCheckError {
+            table: "animals".to_owned(),
+            rowid: 1,
+            parent: "food".to_owned(),
+            fkid: 0
+        }]
+    );
+    assert!(!rehearsal.is_success());
+}
+
+#[test]
+fn rehearse_too_far_ahead_test() {
+    let conn = animals_conn();
+    conn.pragma_update(None, "user_version", 2).unwrap();
+    let rehearsal = Migrations::new(vec![m_valid10_up()])
+        .rehearse(&conn)
+        .unwrap();
+    assert_eq!(
+        rehearsal.error,
+        Some(Error::MigrationDefinition(
+            MigrationDefinitionError::DatabaseTooFarAhead
+        ))
+    );
+
+    assert_eq!(
+        Migrations::new(vec![]).rehearse(&conn),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::NoMigrationsDefined
+        ))
+    );
+}
+
+#[test]
+fn rehearse_with_file_test() {
+    let path = std::env::temp_dir().join(format!(
+        "rusqlite_migration_rehearse_with_file_test_{}.sqlite3",
+        std::process::id()
+    ));
+    let conn = animals_conn();
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;"),
+    ]);
+
+    let rehearsal = migrations.rehearse_with_file(&conn, &path).unwrap();
+    assert!(rehearsal.is_success());
+    assert_eq!(user_version(&Connection::open(&path).unwrap()), 2);
+    assert_eq!(user_version(&conn), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rehearse_with_file_rolled_back_test() {
+    let path = std::env::temp_dir().join(format!(
+        "rusqlite_migration_rehearse_with_file_rolled_back_test_{}.sqlite3",
+        std::process::id()
+    ));
+    let conn = animals_conn();
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);"),
+        M::up("ALTER TABLE animals ADD COLUMN legs INTEGER;"),
+        M::up("CREATE UNIQUE INDEX animals_name ON animals(name);"),
+    ]);
+
+    // Like with to_latest, the migrations that succeeded are rolled back with the failed one
+    let rehearsal = migrations.rehearse_with_file(&conn, &path).unwrap();
+    assert_eq!(rehearsal.steps.len(), 1);
+    assert!(rehearsal.error.is_some());
+    let copy = Connection::open(&path).unwrap();
+    assert_eq!(user_version(&copy), 1);
+    copy.execute("INSERT INTO animals (name, legs) VALUES ('cat', 4)", [])
+        .unwrap_err();
+
+    drop(copy);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rehearse_schema_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "ATTACH DATABASE ':memory:' AS cache;
+        CREATE TABLE cache.animals (name TEXT); INSERT INTO cache.animals VALUES ('dog');
+        PRAGMA cache.user_version = 1;",
+    )
+    .unwrap();
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE cache.animals (name TEXT);"),
+        M::up("ALTER TABLE cache.animals ADD COLUMN legs INTEGER;").foreign_key_check(),
+        M::up("CREATE UNIQUE INDEX cache.animals_name ON animals(name);"),
+    ])
+    .schema("cache");
+
+    let rehearsal = migrations.rehearse(&conn).unwrap();
+    assert!(rehearsal.is_success(), "{rehearsal}");
+    assert_eq!(rehearsal.from_version, 1);
+    assert_eq!(rehearsal.steps.len(), 2);
+    assert_eq!(
+        conn.query_row("PRAGMA cache.user_version", [], |row| row.get::<_, i64>(0)),
+        Ok(1)
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "rusqlite_migration_rehearse_schema_test_{}.sqlite3",
+        std::process::id()
+    ));
+    let rehearsal = migrations.rehearse_with_file(&conn, &path).unwrap();
+    assert!(rehearsal.is_success(), "{rehearsal}");
+    let copy = Connection::open(&path).unwrap();
+    assert_eq!(user_version(&copy), 3);
+    copy.execute("INSERT INTO animals (name, legs) VALUES ('dog', 4)", [])
+        .unwrap_err();
+
+    drop(copy);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rehearse_reordered_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT);").id("animals"),
+        M::up("CREATE TABLE food (name TEXT);").id("food"),
+    ])
+    .to_latest(&mut conn)
+    .unwrap();
+
+    let rehearsal = Migrations::new(vec![
+        M::up("CREATE TABLE food (name TEXT);").id("food"),
+        M::up("CREATE TABLE animals (name TEXT);").id("animals"),
+        m_valid10_up(),
+    ])
+    .rehearse(&conn)
+    .unwrap();
+    assert!(!rehearsal.is_success());
+    assert!(rehearsal.steps.is_empty());
+    assert!(matches!(
+        rehearsal.error,
+        Some(Error::MigrationsReordered { version: 1, .. })
+    ));
+}
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use rusqlite::Connection;
+
+use crate::{user_version_in, Error, Migrations, M};
+
+fn runs(conn: &Connection) -> Vec<i64> {
+    let mut stmt = conn.prepare("SELECT n FROM runs").unwrap();
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+fn base() -> Migrations<'static> {
+    Migrations::new(vec![M::up("CREATE TABLE runs (n INTEGER);")])
+}
+
+#[test]
+fn changed_repeatable_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    base()
+        .repeatable("log", "INSERT INTO runs VALUES (1);")
+        .to_latest(&mut conn)
+        .unwrap();
+    assert_eq!(runs(&conn), [1]);
+
+    // Unchanged, or only comments and whitespace changed
+    base()
+        .repeatable("log", "INSERT INTO runs VALUES (1);")
+        .to_latest(&mut conn)
+        .unwrap();
+    base()
+        .repeatable("log", "-- Log\nINSERT INTO runs\n    VALUES (1);")
+        .to_latest(&mut conn)
+        .unwrap();
+    assert_eq!(runs(&conn), [1]);
+
+    base()
+        .repeatable("log", "INSERT INTO runs VALUES (2);")
+        .to_latest(&mut conn)
+        .unwrap();
+    assert_eq!(runs(&conn), [1, 2]);
+
+    // The last one registered with a name replaces the others
+    base()
+        .repeatable("log", "INSERT INTO runs VALUES (3);")
+        .repeatable("log", "INSERT INTO runs VALUES (2);")
+        .to_latest(&mut conn)
+        .unwrap();
+    assert_eq!(runs(&conn), [1, 2]);
+}
+
+#[test]
+fn repeatable_order_test() {
+    // Applied after the other migrations, by name
+    let migrations = Migrations::new(vec![M::up(
+        "CREATE TABLE animals (name TEXT, legs INTEGER);",
+    )])
+    .repeatable(
+        "b_dog_names",
+        "DROP VIEW IF EXISTS dog_names; CREATE VIEW dog_names AS SELECT name FROM dogs;",
+    )
+    .repeatable(
+        "a_dogs",
+        "DROP VIEW IF EXISTS dogs; CREATE VIEW dogs AS SELECT * FROM animals WHERE legs = 4;",
+    );
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    conn.prepare("SELECT * FROM dog_names").unwrap();
+    migrations.validate().unwrap();
+
+    // Not part of the schema versions
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+    migrations.to_version(&mut conn, 1).unwrap();
+}
+
+#[test]
+fn appended_repeatable_test() {
+    let module = Migrations::new(vec![M::up("INSERT INTO runs VALUES (10);")])
+        .repeatable("log", "INSERT INTO runs VALUES (2);")
+        .repeatable("module_log", "INSERT INTO runs VALUES (3);");
+    let migrations = base()
+        .repeatable("log", "INSERT INTO runs VALUES (1);")
+        .module("module", module);
+
+    // The repeatable migration of the module replaces the one with the same name
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    assert_eq!(runs(&conn), [10, 2, 3]);
+}
+
+#[test]
+fn failing_repeatable_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    let err = base()
+        .repeatable(
+            "log",
+            "INSERT INTO runs VALUES (1);\nINSERT INTO missing VALUES (1);",
+        )
+        .to_latest(&mut conn)
+        .unwrap_err();
+    assert!(matches!(&err, Error::Repeatable { name, .. } if name == "log"));
+    insta::assert_snapshot!(err, @"rusqlite_migration error: repeatable migration “log” failed: rusqlite_migration error in statement 'INSERT INTO missing VALUES (1);' at line 2, column 1 (byte 29): no such table: missing");
+
+    // The migrations are rolled back with the repeatable one
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+    conn.prepare("SELECT n FROM runs").unwrap_err();
+
+    // With the database already up to date
+    base().to_latest(&mut conn).unwrap();
+    base()
+        .repeatable(
+            "log",
+            "INSERT INTO runs VALUES (1);
+INSERT INTO missing VALUES (1);",
+        )
+        .to_latest(&mut conn)
+        .unwrap_err();
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+    assert!(runs(&conn).is_empty());
+}
+
+#[cfg(feature = "from-directory")]
+#[test]
+fn from_directory_test() {
+    use include_dir::{include_dir, Dir};
+
+    use crate::MigrationsBuilder;
+
+    static MIGRATIONS_DIR: Dir =
+        include_dir!("$CARGO_MANIFEST_DIR/../examples/from-directory/migrations");
+
+    let migrations = Migrations::from_directory(&MIGRATIONS_DIR).unwrap();
+    assert_eq!(migrations.ms.len(), 3);
+    assert_eq!(migrations.repeatable.len(), 1);
+    assert_eq!(migrations.repeatable[0].name, "friend_names");
+    assert_eq!(
+        MigrationsBuilder::from_directory(&MIGRATIONS_DIR)
+            .unwrap()
+            .finalize(),
+        migrations
+    );
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_latest(&mut conn).unwrap();
+    conn.prepare("SELECT * FROM friend_names").unwrap();
+}
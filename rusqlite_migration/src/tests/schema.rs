@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rusqlite::Connection;
+
+use crate::schema::read_schema;
+use crate::tests::helpers::{
+    all_valid_down, all_valid_up, m_valid10_down, m_valid10_up, m_valid20_down,
+};
+use crate::{
+    Error, MigrationDefinitionError, MigrationDirection, Migrations, Schema, SchemaDiff,
+    SchemaVersion, M,
+};
+
+#[test]
+fn read_schema_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    assert_eq!(read_schema(&conn, None), Ok(vec![]));
+
+    conn.execute_batch("CREATE TABLE t(a PRIMARY KEY, b); CREATE INDEX i ON t(b);")
+        .unwrap();
+    let schema = read_schema(&conn, None).unwrap();
+    // The automatic index for the primary key is not included
+    assert_eq!(schema.len(), 2);
+    assert_eq!(schema[0].kind, "index");
+    assert_eq!(schema[0].name, "i");
+    assert_eq!(schema[0].table, "t");
+    assert_eq!(
+        schema[1].sql.as_deref(),
+        Some("CREATE TABLE t(a PRIMARY KEY,b)")
+    );
+}
+
+#[test]
+fn validate_reversible_valid_test() {
+    assert_eq!(
+        Migrations::new(all_valid_down()).validate_reversible(),
+        Ok(())
+    );
+    // Without down migrations, there is nothing to round trip
+    assert_eq!(
+        Migrations::new(all_valid_up()).validate_reversible(),
+        Ok(())
+    );
+}
+
+#[test]
+fn validate_reversible_empty_test() {
+    assert_eq!(
+        Migrations::new(vec![]).validate_reversible(),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::NoMigrationsDefined
+        ))
+    );
+}
+
+#[test]
+fn validate_reversible_invalid_test() {
+    let migrations = Migrations::new(vec![
+        m_valid10_down(),
+        m_valid20_down(),
+        M::up("CREATE TABLE t3(a); CREATE INDEX i3 ON t3(a);").down("DROP INDEX i3;"),
+    ]);
+    assert_eq!(
+        migrations.validate_reversible(),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::SchemaNotRestored { migration_index: 2 }
+        ))
+    );
+
+    // A down migration that fails is reported as such
+    let migrations = Migrations::new(vec![m_valid10_up().down("DROP TABLE does_not_exist;")]);
+    assert!(matches!(
+        migrations.validate_reversible(),
+        Err(Error::Migration {
+            direction: MigrationDirection::Down,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn schema_read_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    assert_eq!(Schema::read(&conn), Ok(Schema::default()));
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE parent(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+        CREATE TABLE child(
+            a, b DEFAULT 'x',
+            parent_id INTEGER REFERENCES parent(id) ON DELETE CASCADE,
+            PRIMARY KEY (b, a)
+        );
+        CREATE INDEX child_expr ON child(a, lower(b)) WHERE a > 0;
+        CREATE VIEW v AS SELECT   a FROM child;
+        CREATE TRIGGER tr AFTER INSERT ON parent BEGIN
+            INSERT INTO child(a) VALUES (new.id);
+        END;
+    "#,
+    )
+    .unwrap();
+
+    let schema = Schema::read(&conn).unwrap();
+    insta::assert_debug_snapshot!(schema);
+    insta::assert_snapshot!(schema);
+}
+
+#[test]
+fn schema_read_attached_test() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        ATTACH DATABASE ':memory:' AS "other db";
+        CREATE TABLE "other db".parent(id INTEGER PRIMARY KEY);
+        CREATE TABLE "other db".child(a REFERENCES parent(id), b);
+        CREATE INDEX "other db".child_b ON child(b);
+        CREATE TABLE main.unrelated(a);
+    "#,
+    )
+    .unwrap();
+    let attached = Schema::read_in(&conn, Some("other db")).unwrap();
+
+    let main = Connection::open_in_memory().unwrap();
+    main.execute_batch(
+        r#"
+        CREATE TABLE parent(id INTEGER PRIMARY KEY);
+        CREATE TABLE child(a REFERENCES parent(id), b);
+        CREATE INDEX child_b ON child(b);
+    "#,
+    )
+    .unwrap();
+    assert_eq!(attached, Schema::read(&main).unwrap());
+}
+
+#[test]
+fn schema_snapshots_attached_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE cache.t(a);").down("DROP TABLE cache.t;"),
+        M::up("CREATE INDEX cache.i ON t(a);").down("DROP INDEX cache.i;"),
+    ])
+    .schema("cache");
+    let snapshots = migrations.schema_snapshots().unwrap();
+    assert_eq!(snapshots.len(), 3);
+    assert_eq!(snapshots[2].1.tables[0].indexes[0].name, "i");
+    assert_eq!(migrations.validate(), Ok(()));
+    assert_eq!(migrations.validate_reversible(), Ok(()));
+}
+
+#[test]
+fn schema_read_sqlite_like_names_test() {
+    // Only the internal `sqlite_` objects are ignored
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE sqliteXtable(a); CREATE TABLE t(a UNIQUE);")
+        .unwrap();
+    let schema = Schema::read(&conn).unwrap();
+    assert_eq!(
+        schema.tables.iter().map(|t| &*t.name).collect::<Vec<_>>(),
+        ["sqliteXtable", "t"]
+    );
+}
+
+#[test]
+fn schema_snapshots_test() {
+    let snapshots = Migrations::new(all_valid_down())
+        .schema_snapshots()
+        .unwrap();
+    assert_eq!(snapshots.len(), all_valid_down().len() + 1);
+    assert_eq!(snapshots[0], (SchemaVersion::NoneSet, Schema::default()));
+    for (i, (version, _)) in snapshots.iter().enumerate() {
+        assert_eq!(usize::from(version), i);
+    }
+    insta::assert_snapshot!(snapshots
+        .iter()
+        .map(|(version, schema)| format!("-- {version}\n{schema}"))
+        .collect::<String>());
+
+    assert_eq!(
+        Migrations::new(vec![]).schema_snapshots(),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::NoMigrationsDefined
+        ))
+    );
+}
+
+#[test]
+fn schema_diff_test() {
+    let old = Connection::open_in_memory().unwrap();
+    let new = Connection::open_in_memory().unwrap();
+    old.execute_batch(
+        r#"
+        CREATE TABLE removed(a);
+        CREATE TABLE kept(a, b TEXT, c CHECK (c > 0));
+        CREATE TABLE parent(id PRIMARY KEY);
+        CREATE INDEX kept_a ON kept(a);
+        CREATE INDEX kept_b ON kept(b);
+        CREATE VIEW v AS SELECT a FROM kept;
+        CREATE TRIGGER tr AFTER INSERT ON kept BEGIN SELECT 1; END;
+    "#,
+    )
+    .unwrap();
+    new.execute_batch(
+        r#"
+        CREATE TABLE added(a);
+        CREATE TABLE kept(a, b INTEGER NOT NULL, c CHECK (c > 1), d REFERENCES parent(id));
+        CREATE TABLE parent(id PRIMARY KEY);
+        CREATE INDEX kept_a ON kept(a, b);
+        CREATE INDEX kept_d ON kept(d);
+        CREATE VIEW v AS SELECT a, b FROM kept;
+    "#,
+    )
+    .unwrap();
+
+    let diff = SchemaDiff::between(&old, &new).unwrap();
+    assert!(!diff.is_empty());
+    assert_eq!(diff.tables.added.len(), 1);
+    assert_eq!(diff.tables.removed.len(), 1);
+    assert_eq!(diff.changed_tables.len(), 1);
+    let kept = &diff.changed_tables[0];
+    assert_eq!(kept.columns.added.len(), 1);
+    assert_eq!(kept.columns.changed.len(), 1);
+    assert_eq!(kept.foreign_keys.added.len(), 1);
+    assert!(kept.sql.is_some());
+    assert_eq!(diff.views.changed.len(), 1);
+    assert_eq!(diff.triggers.removed.len(), 1);
+    insta::assert_snapshot!(diff);
+
+    assert!(SchemaDiff::between(&new, &new).unwrap().is_empty());
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::sql::{
+    checksum, line_column, normalize_sql, parse_create_table, rename_create_table,
+    split_statements, tokenize, TableItem, TokenKind,
+};
+
+#[test]
+fn tokenize_test() {
+    let sql = "SELECT 'it''s', \"a \"\"b\"\"\", [c d] -- comment\n/* multi\nline */;";
+    let tokens: Vec<_> = tokenize(sql)
+        .into_iter()
+        .filter(|t| t.is_significant())
+        .map(|t| (t.kind, t.text))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenKind::Word, "SELECT"),
+            (TokenKind::String, "'it''s'"),
+            (TokenKind::Punct, ","),
+            (TokenKind::Quoted, "\"a \"\"b\"\"\""),
+            (TokenKind::Punct, ","),
+            (TokenKind::Quoted, "[c d]"),
+            (TokenKind::Punct, ";"),
+        ]
+    );
+    // Offsets allow to rebuild the text
+    let rebuilt: String = tokenize(sql).iter().map(|t| t.text).collect();
+    assert_eq!(rebuilt, sql);
+    assert_eq!(tokenize(sql)[2].start, 7);
+
+    // Unterminated tokens don’t panic
+    for sql in ["'abc", "\"abc", "[abc", "/* abc", "-- abc"] {
+        assert_eq!(tokenize(sql).len(), 1, "{sql}");
+    }
+}
+
+#[test]
+fn normalize_sql_test() {
+    assert_eq!(normalize_sql("CREATE TABLE t(a, b)"), "CREATE TABLE t(a,b)");
+    assert_eq!(
+        normalize_sql("CREATE   TABLE t (\n  a , -- comment\n  b\n)"),
+        "CREATE TABLE t(a,b)"
+    );
+    // Quoted strings and identifiers requiring quotes are left untouched
+    assert_eq!(
+        normalize_sql("CREATE TABLE \"t  1\" (a DEFAULT ' (x , y) ', [b  c], \"d\")"),
+        "CREATE TABLE \"t  1\"(a DEFAULT ' (x , y) ',[b  c],d)"
+    );
+}
+
+#[test]
+fn parse_create_table_test() {
+    let create = parse_create_table(
+        "CREATE TABLE IF NOT EXISTS main.\"t\" (a INTEGER PRIMARY KEY, \"b c\" CHECK (b > 0), \
+        CONSTRAINT pk UNIQUE (a, \"b c\"))",
+    )
+    .unwrap();
+    assert_eq!(create.name.identifier(), "t");
+    assert_eq!(
+        create.items,
+        vec![
+            TableItem::Column {
+                name: "a".to_owned(),
+                definition: "a INTEGER PRIMARY KEY"
+            },
+            TableItem::Column {
+                name: "b c".to_owned(),
+                definition: "\"b c\" CHECK (b > 0)"
+            },
+            TableItem::Constraint("CONSTRAINT pk UNIQUE (a, \"b c\")"),
+        ]
+    );
+
+    assert_eq!(parse_create_table("CREATE TABLE t AS SELECT 1"), None);
+    assert_eq!(parse_create_table("CREATE INDEX i ON t(a)"), None);
+    assert_eq!(parse_create_table("CREATE TABLE t(a"), None);
+}
+
+#[test]
+fn rename_create_table_test() {
+    assert_eq!(
+        rename_create_table("CREATE TEMP TABLE t(a)", "new t").as_deref(),
+        Some("CREATE TEMP TABLE \"new t\"(a)")
+    );
+    assert_eq!(rename_create_table("CREATE VIEW v AS SELECT 1", "x"), None);
+}
+
+#[test]
+fn split_statements_test() {
+    let sql = "-- header\nCREATE TABLE t(a); /* ; */ INSERT INTO t VALUES (';');\n\
+        CREATE TEMP TRIGGER tr AFTER INSERT ON t BEGIN\n\
+          UPDATE t SET a = CASE WHEN a THEN 1 ELSE 0 END;\n\
+          DELETE FROM t;\n\
+        END;\n\
+        SELECT 1 -- no semicolon\n";
+    let statements: Vec<_> = split_statements(sql)
+        .into_iter()
+        .map(|s| (s.start, s.text))
+        .collect();
+    assert_eq!(
+        statements,
+        vec![
+            (10, "CREATE TABLE t(a);"),
+            (37, "INSERT INTO t VALUES (';');"),
+            (
+                65,
+                "CREATE TEMP TRIGGER tr AFTER INSERT ON t BEGIN\n\
+                  UPDATE t SET a = CASE WHEN a THEN 1 ELSE 0 END;\n\
+                  DELETE FROM t;\n\
+                END;"
+            ),
+            (180, "SELECT 1 -- no semicolon"),
+        ]
+    );
+    for (start, text) in statements {
+        assert_eq!(&sql[start..start + text.len()], text);
+    }
+
+    assert!(split_statements("").is_empty());
+    assert!(split_statements("-- only a comment\n").is_empty());
+}
+
+#[test]
+fn line_column_test() {
+    let sql = "SELECT 1;\n  SELECT 'é'; SELECT 2;";
+    assert_eq!(line_column(sql, 0), (1, 1));
+    assert_eq!(line_column(sql, 12), (2, 3));
+    assert_eq!(line_column(sql, sql.rfind("SELECT").unwrap()), (2, 15));
+}
+
+#[test]
+fn checksum_test() {
+    let sum = checksum("CREATE TABLE t(a);");
+    assert_eq!(sum.len(), 16);
+    assert_eq!(sum, checksum("-- Comment\nCREATE  TABLE t ( a ) ;"));
+    assert_ne!(sum, checksum("CREATE TABLE t(b);"));
+    // Stable across versions
+    insta::assert_snapshot!(sum, @"5fec0176159d5b46");
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::time::{Duration, SystemTime};
+
+use rusqlite::{Connection, Transaction};
+
+use crate::{Migrations, SchemaVersion, M};
+
+fn ms() -> Vec<M<'static>> {
+    vec![
+        M::up("CREATE TABLE animals (name TEXT);")
+            .down("DROP TABLE animals;")
+            .comment("Animals"),
+        M::up_with_hook("CREATE TABLE food (name TEXT);", |_: &Transaction| Ok(()))
+            .down("DROP TABLE food;")
+            .foreign_key_check(),
+        M::up("CREATE TABLE plants (name TEXT);"),
+    ]
+}
+
+#[test]
+fn status_test() {
+    let migrations = Migrations::new(ms());
+    let mut conn = Connection::open_in_memory().unwrap();
+
+    let status = migrations.status(&conn).unwrap();
+    assert_eq!(status.version, SchemaVersion::NoneSet);
+    assert_eq!(status.pending().count(), 3);
+    assert!(status.unknown_versions.is_empty());
+
+    migrations.to_version(&mut conn, 2).unwrap();
+    let status = migrations.status(&conn).unwrap();
+    assert_eq!(
+        status.pending().map(|m| m.index).collect::<Vec<_>>(),
+        vec![2]
+    );
+    // No history without modules
+    assert!(status.migrations.iter().all(|m| m.applied_at.is_none()));
+    insta::assert_debug_snapshot!(status);
+    insta::assert_snapshot!(status, @r"
+    database at version 2 (inside)
+      migration 0 [applied] (Animals)
+      migration 1 [applied], hooks, foreign key check
+      migration 2 [pending], irreversible
+    ");
+}
+
+#[test]
+fn status_unknown_versions_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    Migrations::new(ms()).to_latest(&mut conn).unwrap();
+
+    let older = Migrations::new(ms()[..1].to_vec());
+    let status = older.status(&conn).unwrap();
+    assert_eq!(status.unknown_versions, vec![2, 3]);
+    assert!(status.migrations[0].applied);
+    insta::assert_snapshot!(status, @r"
+    database at version 3 (outside)
+      migration 0 [applied] (Animals)
+      versions 2 to 3 are unknown to this program
+    ");
+
+    let status = Migrations::new(ms()[..2].to_vec()).status(&conn).unwrap();
+    insta::assert_snapshot!(status, @r"
+    database at version 3 (outside)
+      migration 0 [applied] (Animals)
+      migration 1 [applied], hooks, foreign key check
+      version 3 is unknown to this program
+    ");
+}
+
+#[test]
+fn status_applied_at_test() {
+    let before = SystemTime::now() - Duration::from_secs(1);
+    let migrations = Migrations::new(vec![]).module("core", Migrations::new(ms()));
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations.to_version(&mut conn, 2).unwrap();
+
+    let status = migrations.status(&conn).unwrap();
+    let after = SystemTime::now() + Duration::from_secs(1);
+    for m in &status.migrations[..2] {
+        let applied_at = m.applied_at.unwrap();
+        assert!(before <= applied_at && applied_at <= after);
+    }
+    assert_eq!(status.migrations[2].applied_at, None);
+
+    // Reverted migrations are not applied anymore
+    migrations.to_version(&mut conn, 1).unwrap();
+    assert_eq!(
+        migrations.status(&conn).unwrap().migrations[1].applied_at,
+        None
+    );
+}
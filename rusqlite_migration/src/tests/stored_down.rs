@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rusqlite::{Connection, Transaction};
+
+use crate::{
+    user_version_in, DowngradePolicy, Error, MigrationDefinitionError, MigrationDirection,
+    Migrations, Schema, M,
+};
+
+const MS: &[M] = &[
+    M::up("CREATE TABLE animals (name TEXT);").down("DROP TABLE animals;"),
+    M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE food;"),
+    M::up("ALTER TABLE food ADD COLUMN kind TEXT;").down("ALTER TABLE food DROP COLUMN kind;"),
+];
+
+fn stored(conn: &Connection) -> Vec<(i64, Option<String>)> {
+    let mut stmt = conn
+        .prepare("SELECT version, sql FROM rusqlite_migration_down ORDER BY version")
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn revert_unknown_migrations_test() {
+    let newer = Migrations::from_slice(MS).store_down_migrations();
+    let older = Migrations::from_slice(&MS[..1]).store_down_migrations();
+    let mut conn = Connection::open_in_memory().unwrap();
+
+    newer.to_latest(&mut conn).unwrap();
+    assert_eq!(stored(&conn).len(), 3);
+
+    older.to_latest(&mut conn).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(1));
+    assert_eq!(
+        stored(&conn),
+        vec![(1, Some(String::from("DROP TABLE animals;")))]
+    );
+    assert_eq!(
+        Schema::read(&conn).unwrap(),
+        older.schema_snapshots().unwrap()[1].1
+    );
+
+    // Unknown migrations can be reverted down to any known version
+    newer.to_latest(&mut conn).unwrap();
+    older.to_version(&mut conn, 0).unwrap();
+    assert_eq!(user_version_in(&conn, None), Ok(0));
+    assert!(stored(&conn).is_empty());
+}
+
+#[test]
+fn revert_unknown_migrations_disabled_test() {
+    let newer = Migrations::from_slice(MS).store_down_migrations();
+    let mut conn = Connection::open_in_memory().unwrap();
+    newer.to_latest(&mut conn).unwrap();
+
+    let older = Migrations::from_slice(&MS[..1]);
+    assert_eq!(
+        older.to_latest(&mut conn),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DatabaseTooFarAhead
+        ))
+    );
+    // Going down without the option still forgets stale down migrations
+    Migrations::from_slice(MS).to_version(&mut conn, 1).unwrap();
+    assert_eq!(stored(&conn).len(), 1);
+}
+
+#[test]
+fn revert_unknown_migrations_not_stored_test() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    // Applied by a program not storing down migrations
+    Migrations::from_slice(MS).to_latest(&mut conn).unwrap();
+
+    let older = Migrations::from_slice(&MS[..1]).store_down_migrations();
+    assert_eq!(
+        older.to_latest(&mut conn),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DatabaseTooFarAhead
+        ))
+    );
+
+    // Down hooks can’t be stored
+    let mut conn = Connection::open_in_memory().unwrap();
+    let newer = Migrations::new(vec![
+        MS[0].clone(),
+        M::up("CREATE TABLE food (name TEXT);")
+            .down_with_hook("DROP TABLE food;", |_: &Transaction| Ok(())),
+    ])
+    .store_down_migrations();
+    newer.to_latest(&mut conn).unwrap();
+    assert_eq!(stored(&conn)[1], (2, None));
+    assert_eq!(
+        older.to_latest(&mut conn),
+        Err(Error::MigrationDefinition(
+            MigrationDefinitionError::DatabaseTooFarAhead
+        ))
+    );
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+}
+
+#[test]
+fn revert_unknown_migrations_failure_test() {
+    let newer = Migrations::new(vec![
+        MS[0].clone(),
+        M::up("CREATE TABLE food (name TEXT);").down("DROP TABLE missing;"),
+    ])
+    .store_down_migrations();
+    let mut conn = Connection::open_in_memory().unwrap();
+    newer.to_latest(&mut conn).unwrap();
+
+    let older = Migrations::from_slice(&MS[..1]).store_down_migrations();
+    assert!(matches!(
+        older.to_version(&mut conn, 0),
+        Err(Error::Migration {
+            migration_index: 1,
+            direction: MigrationDirection::Down,
+            target_version: 0,
+            comment: None,
+            ..
+        })
+    ));
+    assert_eq!(user_version_in(&conn, None), Ok(2));
+
+    let older = older.downgrade_policy(DowngradePolicy::NonDestructiveOnly);
+    assert_eq!(
+        older.to_latest(&mut conn),
+        Err(Error::DowngradeDenied {
+            migration_index: 1,
+            policy: DowngradePolicy::NonDestructiveOnly
+        })
+    );
+}
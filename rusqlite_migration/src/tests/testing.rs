@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright Clément Joly and contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use crate::{
+    testing::{FuzzStep, Fuzzer, MigrationTest},
+    Migrations, M,
+};
+
+use super::helpers::{all_valid_down, m_valid10_down, m_valid20_down};
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up("CREATE TABLE animals (name TEXT, legs INTEGER);").down("DROP TABLE animals;"),
+        M::up("ALTER TABLE animals ADD COLUMN wings INTEGER; UPDATE animals SET wings = 4 - legs;")
+            .down("ALTER TABLE animals DROP COLUMN wings;")
+            .comment("Count wings"),
+        M::up("CREATE UNIQUE INDEX animals_name ON animals(name);").comment("Unique names"),
+    ])
+}
+
+#[test]
+fn migration_test_up_down_test() {
+    let migrations = migrations();
+    let mut test = MigrationTest::at_version(&migrations, 1);
+    assert_eq!(test.version(), 1);
+    test.insert("animals", &[("name", &"dog"), ("legs", &4)])
+        .execute(
+            "INSERT INTO animals (name, legs) VALUES (?1, ?2)",
+            ("bird", 2),
+        );
+
+    test.up();
+    assert_eq!(test.version(), 2);
+    let rows = test.query("SELECT name, wings FROM animals ORDER BY name", [], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    });
+    assert_eq!(rows, vec![("bird".to_owned(), 2), ("dog".to_owned(), 0)]);
+
+    test.down();
+    let legs: Vec<i64> = test.query_values("SELECT legs FROM animals ORDER BY name", []);
+    assert_eq!(legs, vec![2, 4]);
+}
+
+#[test]
+#[should_panic(
+    expected = "cannot query “SELECT wings FROM animals” at version 1, after migration 0"
+)]
+fn migration_test_query_panic_test() {
+    let migrations = migrations();
+    let test = MigrationTest::at_version(&migrations, 1);
+    test.query_values::<i64>("SELECT wings FROM animals", []);
+}
+
+#[test]
+fn migration_test_failure_message_test() {
+    let migrations = migrations();
+    let mut test = MigrationTest::at_version(&migrations, 2);
+    test.execute_batch("INSERT INTO animals (name, legs) VALUES ('dog', 4), ('dog', 3);");
+
+    let message = test.try_to_version(3).unwrap_err();
+    assert!(
+        message.starts_with(
+            "migration 2 (“Unique names”) failed when migrating up from version 2 to 3: "
+        ),
+        "{message}"
+    );
+
+    let message = test.try_to_version(4).unwrap_err();
+    assert_eq!(
+        message,
+        "cannot migrate to version 4, the last version is 3"
+    );
+
+    test.to_version(0);
+    assert_eq!(test.version(), 0);
+}
+
+#[test]
+#[should_panic(expected = "migration 2 (“Unique names”) failed when migrating down")]
+fn migration_test_down_panic_test() {
+    let migrations = migrations();
+    MigrationTest::at_version(&migrations, 3).down();
+}
+
+#[test]
+fn migration_test_schema_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE cache.animals (name TEXT);").down("DROP TABLE cache.animals;"),
+        M::up("ALTER TABLE cache.animals ADD COLUMN legs INTEGER;").comment("Count legs"),
+    ])
+    .schema("cache");
+    let mut test = MigrationTest::at_version(&migrations, 1);
+    assert_eq!(test.version(), 1);
+    test.insert("animals", &[("name", &"dog")]);
+    let main_version: i64 = test.query_values("PRAGMA main.user_version", [])[0];
+    assert_eq!(main_version, 0);
+
+    test.up();
+    assert_eq!(test.version(), 2);
+    let legs: Vec<Option<i64>> = test.query_values("SELECT legs FROM cache.animals", []);
+    assert_eq!(legs, vec![None]);
+}
+
+#[test]
+fn migration_test_graph_failure_message_test() {
+    // Applied after the migration defined below it
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE legs (a); CREATE TABLE legs (a);")
+            .id("legs")
+            .depends_on(&["animals"])
+            .comment("Add legs"),
+        M::up("CREATE TABLE animals (name TEXT);")
+            .id("animals")
+            .comment("Add animals"),
+    ]);
+    let mut test = MigrationTest::new(&migrations);
+    let message = test.try_to_version(2).unwrap_err();
+    assert!(
+        message
+            .starts_with("migration 1 (“Add legs”) failed when migrating up from version 1 to 2: "),
+        "{message}"
+    );
+}
+
+#[test]
+fn fuzzer_valid_test() {
+    let valid = Migrations::new(all_valid_down());
+    Fuzzer::new(&valid).run().unwrap();
+
+    // Unique constraints make some migrations fail, depending on the data
+    let migrations = migrations();
+    Fuzzer::new(&migrations).seed(1).run().unwrap();
+}
+
+#[test]
+fn fuzzer_not_reversible_test() {
+    let migrations = Migrations::new(vec![
+        m_valid10_down(),
+        m_valid20_down(),
+        M::up("CREATE INDEX t1_a ON t1(a);").down("DROP TABLE t2;"),
+    ]);
+    let failure = Fuzzer::new(&migrations).run().unwrap_err();
+    assert_eq!(
+        failure.path,
+        vec![
+            FuzzStep {
+                target: 3,
+                data_seed: None
+            },
+            FuzzStep {
+                target: 2,
+                data_seed: None
+            }
+        ]
+    );
+    insta::assert_snapshot!(failure);
+    assert_eq!(
+        Fuzzer::new(&migrations).replay(&failure.path),
+        Err(failure.message)
+    );
+}
+
+#[test]
+fn fuzzer_schema_test() {
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE cache.animals (name TEXT);").down("DROP TABLE cache.animals;"),
+        M::up("CREATE UNIQUE INDEX cache.animals_name ON animals(name);")
+            .down("DROP INDEX cache.animals_name;"),
+    ])
+    .schema("cache");
+    Fuzzer::new(&migrations).seed(1).run().unwrap();
+
+    // The schema of the attached database is compared, not the one of main
+    let migrations = Migrations::new(vec![
+        M::up("CREATE TABLE cache.animals (name TEXT);").down("DROP TABLE cache.animals;"),
+        M::up("CREATE INDEX cache.animals_name ON animals(name);").down("SELECT 1;"),
+    ])
+    .schema("cache");
+    let failure = Fuzzer::new(&migrations).run().unwrap_err();
+    assert!(
+        failure
+            .message
+            .contains("the schema differs from the one of version 1"),
+        "{failure}"
+    );
+}